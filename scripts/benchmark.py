@@ -0,0 +1,139 @@
+#!/usr/bin/env python3
+"""Measures the throughput and peak memory of json-struct on a large generated document.
+
+Usage: scripts/benchmark.py [--size MB] [--depth N] BINARY [BINARY...]
+
+Each binary is run on the same fixture, so that builds can be compared, e.g. a release build of
+the current branch against one of the main branch. The fixture is written to the system's
+temporary directory and reused between runs.
+"""
+
+import argparse
+import json
+import os
+import random
+import resource
+import subprocess
+import sys
+import tempfile
+import time
+
+
+def generate_fixture(path, size_mb):
+    """Writes an array of records mixing nested objects, arrays and all scalar types."""
+    rd = random.Random(42)
+    target = size_mb * 1024 * 1024
+
+    with open(path, "w") as f:
+        f.write("[")
+        written = 0
+        i = 0
+        while written < target:
+            record = {
+                "id": i,
+                "name": "user %d" % i,
+                "active": rd.random() < 0.5,
+                "score": rd.random() * 100,
+                "manager": None,
+                "address": {
+                    "street": "%d Main Street" % rd.randrange(1000),
+                    "city": rd.choice(["London", "Montréal", "Tokyo"]),
+                    "geo": [rd.uniform(-90, 90), rd.uniform(-180, 180)],
+                },
+                "tags": ["tag-%d" % rd.randrange(50) for _ in range(rd.randrange(5))],
+                "first name": "John \"Johnny\"",
+            }
+            chunk = ("," if i else "") + json.dumps(record)
+            f.write(chunk)
+            written += len(chunk)
+            i += 1
+        f.write("]")
+
+
+def generate_deep_fixture(path, depth):
+    with open(path, "w") as f:
+        f.write("[" * depth + "]" * depth)
+
+
+def run(binary, fixture):
+    """Runs the binary on the fixture and returns the elapsed time and its peak RSS in bytes."""
+    start = time.perf_counter()
+    proc = subprocess.run(
+        [binary, "--type", "json", fixture],
+        stdout=subprocess.DEVNULL,
+        stderr=subprocess.PIPE,
+    )
+    elapsed = time.perf_counter() - start
+    after = resource.getrusage(resource.RUSAGE_CHILDREN).ru_maxrss
+
+    if proc.returncode != 0:
+        return elapsed, None, proc.stderr.decode(errors="replace").strip().splitlines()[-1:]
+
+    # ru_maxrss is the maximum over all the children, so each binary is measured from a fresh
+    # interpreter to get its own peak.
+    return elapsed, after * 1024, None
+
+
+def measure(binary, fixture):
+    out = subprocess.run(
+        [sys.executable, __file__, "--measure", binary, fixture],
+        stdout=subprocess.PIPE,
+        check=True,
+    )
+    return json.loads(out.stdout)
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("--size", type=int, default=300, help="fixture size in MB")
+    parser.add_argument("--depth", type=int, default=100_000, help="nesting of the deep fixture")
+    parser.add_argument("--measure", nargs=2, help=argparse.SUPPRESS)
+    parser.add_argument("binaries", nargs="*")
+    args = parser.parse_args()
+
+    if args.measure:
+        elapsed, rss, error = run(*args.measure)
+        print(json.dumps({"elapsed": elapsed, "rss": rss, "error": error}))
+        return
+
+    if not args.binaries:
+        parser.error("at least one binary is required")
+
+    tmp = tempfile.gettempdir()
+    fixture = os.path.join(tmp, "json-struct-bench-%dmb.json" % args.size)
+    deep_fixture = os.path.join(tmp, "json-struct-bench-deep-%d.json" % args.depth)
+
+    if not os.path.exists(fixture):
+        print("Generating %s..." % fixture, file=sys.stderr)
+        generate_fixture(fixture, args.size)
+    if not os.path.exists(deep_fixture):
+        generate_deep_fixture(deep_fixture, args.depth)
+
+    size = os.path.getsize(fixture)
+
+    print("%-40s %12s %12s %14s" % ("binary", "time (s)", "MB/s", "peak RSS (MB)"))
+    for binary in args.binaries:
+        result = measure(binary, fixture)
+        if result["error"]:
+            print("%-40s failed: %s" % (binary, result["error"]))
+            continue
+        print(
+            "%-40s %12.2f %12.1f %14.1f"
+            % (
+                binary,
+                result["elapsed"],
+                size / result["elapsed"] / 1e6,
+                result["rss"] / 1e6,
+            )
+        )
+
+    print()
+    print("Nesting depth of %d:" % args.depth)
+    for binary in args.binaries:
+        result = measure(binary, deep_fixture)
+        status = "ok" if not result["error"] else "failed: %s" % " ".join(result["error"])
+        print("%-40s %s" % (binary, status))
+
+
+if __name__ == "__main__":
+    main()
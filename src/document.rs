@@ -0,0 +1,516 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use indexmap::IndexMap;
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use toml::value::Datetime;
+
+use crate::path::{to_json_string, Segment};
+
+/// toml deserializes datetimes as an object with this single field, holding the datetime as a
+/// string.
+const TOML_DATETIME_FIELD: &str = "$__toml_private_datetime";
+
+pub(super) type Mapping = IndexMap<Key, Node>;
+
+/// The key of an object. YAML mappings can have keys of any type, which are kept apart from
+/// string keys so that `200: OK` and `"200": OK` don't get the same path.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) enum Key {
+    String(String),
+    /// Any other key, as text: scalars are written like values, e.g. `200` or `true`, and
+    /// sequences and mappings in YAML flow style, e.g. `[a, b]`.
+    Other(String),
+}
+
+impl Key {
+    pub(super) fn as_str(&self) -> &str {
+        match self {
+            Key::String(s) | Key::Other(s) => s,
+        }
+    }
+}
+
+impl From<Node> for Key {
+    fn from(node: Node) -> Self {
+        match node {
+            Node::String(s) => Key::String(s),
+            node => {
+                let mut text = String::new();
+                write_flow_style(&node, &mut text);
+                Key::Other(text)
+            }
+        }
+    }
+}
+
+impl From<Key> for Segment {
+    fn from(key: Key) -> Self {
+        match key {
+            Key::String(k) => Segment::Key(k),
+            Key::Other(k) => Segment::OtherKey(k),
+        }
+    }
+}
+
+/// Writes a node on a single line, in YAML flow style. Strings are only quoted when they could
+/// be mistaken for another scalar or contain punctuation.
+fn write_flow_style(node: &Node, out: &mut String) {
+    match node {
+        Node::String(s) => write_flow_string(s, out),
+        Node::Array(a) => {
+            out.push('[');
+            for (i, item) in a.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_flow_style(item, out);
+            }
+            out.push(']');
+        }
+        Node::Object(o) => {
+            out.push('{');
+            for (i, (key, value)) in o.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                match key {
+                    Key::String(s) => write_flow_string(s, out),
+                    Key::Other(s) => out.push_str(s),
+                }
+                out.push_str(": ");
+                write_flow_style(value, out);
+            }
+            out.push('}');
+        }
+        Node::Tagged(tag, value) => {
+            out.push_str(tag);
+            out.push(' ');
+            write_flow_style(value, out);
+        }
+        Node::Binary(b) => {
+            out.push_str("!!binary ");
+            out.push_str(&encode_binary(b));
+        }
+        Node::Merged(value) => write_flow_style(value, out),
+        Node::Alias(alias) => {
+            out.push('*');
+            out.push_str(&alias.name);
+        }
+        Node::Number(Number::Float(x)) if x.is_nan() => out.push_str(".nan"),
+        Node::Number(Number::Float(x)) if x.is_infinite() => {
+            out.push_str(if *x > 0.0 { ".inf" } else { "-.inf" })
+        }
+        // Keys are compared by their text, so numbers are written the same way whatever their
+        // text in the input, e.g. `0x1f` and `31`.
+        Node::Number(n) => out.push_str(&n.value().to_string()),
+        scalar => out.push_str(&scalar.scalar_to_string().unwrap_or_default()),
+    }
+}
+
+fn write_flow_string(s: &str, out: &mut String) {
+    let is_plain = !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_alphanumeric() || "_-./ ".contains(c))
+        && !s.starts_with([' ', '-'])
+        && !s.ends_with(' ')
+        && !matches!(s, "null" | "true" | "false" | "~")
+        && s.parse::<Number>().is_err();
+
+    if is_plain {
+        out.push_str(s);
+    } else {
+        out.push_str(&to_json_string(s));
+    }
+}
+
+/// A document of any of the input formats. Scalars that JSON doesn't have get their own variant,
+/// so that they reach the value writers instead of being coerced to a JSON type.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Node {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    DateTime(Datetime),
+    /// Binary data, from the `!!binary` scalars of YAML.
+    Binary(Vec<u8>),
+    Array(Vec<Node>),
+    /// Objects keep the order of their keys in the input.
+    Object(Mapping),
+    /// A value with a YAML tag, e.g. the `!Ref` of CloudFormation templates. Tags of the core
+    /// schema that only give the type of the value, e.g. `!!str`, are not kept.
+    Tagged(String, Box<Node>),
+    /// A YAML alias, when aliases are kept as references rather than replaced by a copy of the
+    /// value of their anchor.
+    Alias(Alias),
+    /// A value that a YAML mapping got from a merge key (`<<`), when merged values are marked.
+    Merged(Box<Node>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Alias {
+    /// The name of the anchor, e.g. `foo` for `&foo`.
+    pub(super) name: String,
+    /// The path of the anchored value, from the root of its document.
+    pub(super) anchor: Vec<Segment>,
+}
+
+impl Node {
+    /// Returns the value of a key of an object.
+    pub(super) fn get(&self, key: &str) -> Option<&Node> {
+        match self {
+            Node::Object(o) => o.get(&Key::String(key.to_string())),
+            Node::Tagged(_, value) | Node::Merged(value) => value.get(key),
+            _ => None,
+        }
+    }
+
+    pub(super) fn is_null(&self) -> bool {
+        matches!(self, Node::Null)
+    }
+
+    /// Returns the text of a scalar, e.g. to use it in a path. Strings are not quoted.
+    pub(super) fn scalar_to_string(&self) -> Option<String> {
+        match self {
+            Node::Null => Some("null".to_string()),
+            Node::Bool(b) => Some(b.to_string()),
+            Node::Number(n) => Some(n.to_string()),
+            Node::String(s) => Some(s.clone()),
+            Node::DateTime(d) => Some(d.to_string()),
+            Node::Binary(b) => Some(encode_binary(b)),
+            Node::Tagged(tag, value) => value.scalar_to_string().map(|s| format!("{} {}", tag, s)),
+            Node::Merged(value) => value.scalar_to_string(),
+            Node::Array(_) | Node::Object(_) | Node::Alias(_) => None,
+        }
+    }
+}
+
+/// Encodes binary data in base64, as in YAML.
+pub(super) fn encode_binary(bytes: &[u8]) -> String {
+    BASE64.encode(bytes)
+}
+
+/// Decodes base64 encoded binary data. Whitespace is ignored, since YAML allows line breaks.
+pub(super) fn decode_binary(s: &str) -> std::result::Result<Vec<u8>, base64::DecodeError> {
+    let encoded: String = s.split_whitespace().collect();
+    BASE64.decode(encoded)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Number {
+    /// Negative integers. Positive ones are always `UInt`.
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    /// A number whose text in the input isn't the way its value is printed, e.g. `1.50`, `1e3`,
+    /// `0x1f` or an integer that doesn't fit in 64 bits. The text is printed instead.
+    Literal(String, Box<Number>),
+}
+
+impl Number {
+    /// Keeps the text of a number in the input, if its value is printed differently. NaN and
+    /// infinities are always printed the same way, e.g. `inf` for YAML's `.inf`, unlike numbers
+    /// too large for a float, e.g. `1e400`.
+    pub(super) fn with_literal(self, text: &str) -> Number {
+        match self {
+            Number::Float(x) if !x.is_finite() && !text.contains(|c: char| c.is_ascii_digit()) => {
+                self
+            }
+            n if n.to_string() == text => n,
+            n => Number::Literal(text.to_string(), Box::new(n)),
+        }
+    }
+
+    /// Returns the value of the number, without its text in the input.
+    pub(super) fn value(&self) -> &Number {
+        match self {
+            Number::Literal(_, value) => value,
+            n => n,
+        }
+    }
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Int(i) => write!(f, "{}", i),
+            Number::UInt(u) => write!(f, "{}", u),
+            Number::Float(x) if x.is_nan() => f.write_str("nan"),
+            Number::Float(x) if x.is_infinite() => {
+                f.write_str(if *x > 0.0 { "inf" } else { "-inf" })
+            }
+            // Floats are printed with the shortest text that reads back as the same value, with a
+            // fraction or an exponent so that they aren't mistaken for integers, e.g. `1.0` or
+            // `1e100`.
+            Number::Float(x) => write!(f, "{:?}", x),
+            Number::Literal(text, _) => f.write_str(text),
+        }
+    }
+}
+
+impl From<serde_json::Number> for Number {
+    fn from(n: serde_json::Number) -> Self {
+        if let Some(u) = n.as_u64() {
+            Number::UInt(u)
+        } else if let Some(i) = n.as_i64() {
+            Number::Int(i)
+        } else {
+            // serde_json has no float for numbers out of range, e.g. `1e400`.
+            let x = n.as_f64().or_else(|| n.to_string().parse().ok());
+            Number::Float(x.unwrap_or(f64::NAN))
+        }
+    }
+}
+
+/// Parses a JSON number, keeping its text.
+impl FromStr for Number {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<serde_json::Number>()
+            .map(|n| Number::from(n).with_literal(s))
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(NodeVisitor { toml: false })
+    }
+}
+
+/// Loads a TOML document.
+pub(super) fn from_toml(s: &str) -> Result<Node, toml::de::Error> {
+    NodeVisitor { toml: true }.deserialize(toml::Deserializer::new(s))
+}
+
+#[derive(Clone, Copy)]
+struct NodeVisitor {
+    /// Whether the input is TOML, whose datetimes are deserialized as objects. Objects of other
+    /// inputs are never taken for datetimes, whatever their keys.
+    toml: bool,
+}
+
+impl<'de> DeserializeSeed<'de> for NodeVisitor {
+    type Value = Node;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Node, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = Node;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Node, E> {
+        Ok(Node::Bool(b))
+    }
+
+    fn visit_i64<E>(self, i: i64) -> Result<Node, E> {
+        if i < 0 {
+            Ok(Node::Number(Number::Int(i)))
+        } else {
+            Ok(Node::Number(Number::UInt(i as u64)))
+        }
+    }
+
+    fn visit_u64<E>(self, u: u64) -> Result<Node, E> {
+        Ok(Node::Number(Number::UInt(u)))
+    }
+
+    fn visit_f64<E>(self, x: f64) -> Result<Node, E> {
+        Ok(Node::Number(Number::Float(x)))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Node, E> {
+        Ok(Node::String(s.to_string()))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<Node, E> {
+        Ok(Node::String(s))
+    }
+
+    fn visit_none<E>(self) -> Result<Node, E> {
+        Ok(Node::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Node, D::Error> {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_unit<E>(self) -> Result<Node, E> {
+        Ok(Node::Null)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+        let mut array = Vec::new();
+        while let Some(node) = seq.next_element_seed(self)? {
+            array.push(node);
+        }
+
+        Ok(Node::Array(array))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+        let mut object = Mapping::new();
+
+        while let Some(key) = map.next_key_seed(self)? {
+            let is_datetime = matches!(&key, Node::String(s) if s == TOML_DATETIME_FIELD);
+            if self.toml && is_datetime && object.is_empty() {
+                let datetime: String = map.next_value()?;
+                return datetime
+                    .parse()
+                    .map(Node::DateTime)
+                    .map_err(de::Error::custom);
+            }
+
+            object.insert(Key::from(key), map.next_value_seed(self)?);
+        }
+
+        Ok(Node::Object(object))
+    }
+}
+
+#[cfg(test)]
+impl From<serde_json::Value> for Node {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Node::Null,
+            serde_json::Value::Bool(b) => Node::Bool(b),
+            serde_json::Value::Number(n) => Node::Number(n.into()),
+            serde_json::Value::String(s) => Node::String(s),
+            serde_json::Value::Array(a) => Node::Array(a.into_iter().map(Node::from).collect()),
+            serde_json::Value::Object(o) => Node::Object(
+                o.into_iter()
+                    .map(|(k, v)| (Key::String(k), Node::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_deserialize {
+    use serde_json::json;
+
+    use super::{from_toml, Key, Node, Number};
+
+    #[test]
+    fn test_json() {
+        let node: Node = r#"{"b": [1, -1, 1.5, "s", true, null], "a": {}}"#.parse().unwrap();
+
+        assert_eq!(
+            node,
+            Node::from(json!({"b": [1, -1, 1.5, "s", true, null], "a": {}}))
+        );
+        match node {
+            Node::Object(o) => {
+                assert_eq!(o.keys().map(Key::as_str).collect::<Vec<_>>(), ["b", "a"])
+            }
+            _ => panic!("expected an object"),
+        }
+    }
+
+    #[test]
+    fn test_toml_datetimes() {
+        let node = from_toml("dob = 1979-05-27T07:32:00-08:00\nt = 07:32:00").unwrap();
+
+        assert_eq!(
+            node.get("dob"),
+            Some(&Node::DateTime(
+                "1979-05-27T07:32:00-08:00".parse().unwrap()
+            ))
+        );
+        assert_eq!(
+            node.get("t"),
+            Some(&Node::DateTime("07:32:00".parse().unwrap()))
+        );
+    }
+
+    #[test]
+    fn test_private_fields_of_other_inputs() {
+        for input in [
+            r#"{"$__toml_private_datetime": "not a date"}"#,
+            r#"{"$serde_json::private::Number": "1"}"#,
+        ] {
+            let node: Node = input.parse().unwrap();
+            assert!(matches!(node, Node::Object(_)), "{:?}", node);
+
+            let node: Node = serde_yaml::from_str(input).unwrap();
+            assert!(matches!(node, Node::Object(_)), "{:?}", node);
+        }
+    }
+
+    #[test]
+    fn test_number_display() {
+        assert_eq!(Number::Int(-1).to_string(), "-1");
+        assert_eq!(Number::UInt(u64::MAX).to_string(), "18446744073709551615");
+        assert_eq!(Number::Float(1.0).to_string(), "1.0");
+        assert_eq!(Number::Float(1e100).to_string(), "1e100");
+        assert_eq!(Number::Float(1.5e-7).to_string(), "1.5e-7");
+        assert_eq!(Number::Float(1e15).to_string(), "1000000000000000.0");
+        assert_eq!(Number::Float(0.1 + 0.2).to_string(), "0.30000000000000004");
+        assert_eq!(Number::Float(-0.0).to_string(), "-0.0");
+        assert_eq!(Number::Float(f64::INFINITY).to_string(), "inf");
+        assert_eq!(Number::Float(f64::NEG_INFINITY).to_string(), "-inf");
+        assert_eq!(Number::Float(f64::NAN).to_string(), "nan");
+        assert_eq!("0.5".parse::<Number>().unwrap(), Number::Float(0.5));
+        assert!("01".parse::<Number>().is_err());
+    }
+
+    #[test]
+    fn test_number_literals() {
+        for text in [
+            "1.50",
+            "1e3",
+            "-0",
+            "123456789012345678901234567890",
+            "1e400",
+        ] {
+            let n = text.parse::<Number>().unwrap();
+            assert!(matches!(n, Number::Literal(..)), "{:?}", n);
+            assert_eq!(n.to_string(), text);
+        }
+
+        assert_eq!(
+            "1e3".parse::<Number>().unwrap().value(),
+            &Number::Float(1000.0)
+        );
+        assert_eq!("1.0".parse::<Number>().unwrap(), Number::Float(1.0));
+        assert_eq!(
+            "12345678901234567890".parse::<Number>().unwrap(),
+            Number::UInt(12345678901234567890)
+        );
+        assert_eq!(
+            Number::Float(f64::INFINITY).with_literal(".inf"),
+            Number::Float(f64::INFINITY)
+        );
+    }
+
+    #[test]
+    fn test_json_number_literals() {
+        let node: Node = r#"{"price": 10.50, "id": 1e3, "e": 1E5}"#.parse().unwrap();
+
+        assert_eq!(
+            node.get("price"),
+            Some(&Node::Number(Number::Literal(
+                "10.50".to_string(),
+                Box::new(Number::Float(10.5))
+            )))
+        );
+        assert_eq!(
+            node.get("id").and_then(Node::scalar_to_string).as_deref(),
+            Some("1e3")
+        );
+        assert_eq!(
+            node.get("e").and_then(Node::scalar_to_string).as_deref(),
+            Some("1E5")
+        );
+    }
+}
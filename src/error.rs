@@ -11,6 +11,9 @@ pub enum ErrorKind {
     JsonDeserialize,
     YamlDeserialize,
     TomlDeserialize,
+    TomlSerialize,
+    Unflatten,
+    Pattern,
 }
 
 #[derive(Debug)]
@@ -19,6 +22,15 @@ pub struct ErrorImpl {
     error: Box<dyn error::Error + Send + Sync>,
 }
 
+impl Error {
+    pub fn new(kind: ErrorKind, error: impl Into<Box<dyn error::Error + Send + Sync>>) -> Self {
+        Self(Box::new(ErrorImpl {
+            kind,
+            error: error.into(),
+        }))
+    }
+}
+
 impl From<str::Utf8Error> for Error {
     fn from(e: str::Utf8Error) -> Self {
         Self(Box::new(ErrorImpl {
@@ -64,6 +76,24 @@ impl From<toml::de::Error> for Error {
     }
 }
 
+impl From<toml::ser::Error> for Error {
+    fn from(e: toml::ser::Error) -> Self {
+        Self(Box::new(ErrorImpl {
+            kind: ErrorKind::TomlSerialize,
+            error: Box::new(e),
+        }))
+    }
+}
+
+impl From<regex::Error> for Error {
+    fn from(e: regex::Error) -> Self {
+        Self(Box::new(ErrorImpl {
+            kind: ErrorKind::Pattern,
+            error: Box::new(e),
+        }))
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         Display::fmt(&format!("{:?}: {}", &self.0.kind, &self.0.error), f)
@@ -0,0 +1,186 @@
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::error::Result;
+
+/// Selects the lines of output to print with regex patterns, which match either the whole line,
+/// only its path or only its value. A line is printed if it matches one of the patterns of each
+/// kind that is given.
+#[derive(Debug, Default)]
+pub(super) struct Filter {
+    line_patterns: Vec<Regex>,
+    path_patterns: Vec<Regex>,
+    value_patterns: Vec<Regex>,
+    /// Whether the lines that match are the ones left out.
+    invert: bool,
+}
+
+impl Filter {
+    pub(super) fn new(
+        line_patterns: &[String],
+        path_patterns: &[String],
+        value_patterns: &[String],
+        ignore_case: bool,
+        invert: bool,
+    ) -> Result<Self> {
+        let build = |patterns: &[String]| -> Result<Vec<Regex>> {
+            patterns
+                .iter()
+                .map(|p| {
+                    RegexBuilder::new(p)
+                        .case_insensitive(ignore_case)
+                        .build()
+                        .map_err(Into::into)
+                })
+                .collect()
+        };
+
+        Ok(Self {
+            line_patterns: build(line_patterns)?,
+            path_patterns: build(path_patterns)?,
+            value_patterns: build(value_patterns)?,
+            invert,
+        })
+    }
+
+    /// Returns the spans of the line that the patterns match, or `None` if the line is left out.
+    /// `path` and `value` are the spans of the path and of the value within the line. The lines
+    /// kept by an inverted filter have no spans.
+    pub(super) fn find(
+        &self,
+        line: &str,
+        path: Range<usize>,
+        value: Range<usize>,
+    ) -> Option<Vec<Range<usize>>> {
+        let mut spans = Vec::new();
+        let mut is_match = true;
+
+        for (patterns, span) in [
+            (&self.line_patterns, 0..line.len()),
+            (&self.path_patterns, path),
+            (&self.value_patterns, value),
+        ] {
+            if patterns.is_empty() {
+                continue;
+            }
+
+            let text = &line[span.clone()];
+            is_match &= patterns.iter().any(|p| p.is_match(text));
+            spans.extend(
+                patterns
+                    .iter()
+                    .flat_map(|p| p.find_iter(text))
+                    .filter(|m| !m.is_empty())
+                    .map(|m| span.start + m.start()..span.start + m.end()),
+            );
+        }
+
+        match (is_match, self.invert) {
+            (true, false) => Some(merge_spans(spans)),
+            (false, true) => Some(Vec::new()),
+            _ => None,
+        }
+    }
+}
+
+/// Sorts the spans and merges the ones that overlap.
+fn merge_spans(mut spans: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    spans.sort_by_key(|s| s.start);
+
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(spans.len());
+    for span in spans {
+        match merged.last_mut() {
+            Some(last) if span.start <= last.end => last.end = last.end.max(span.end),
+            _ => merged.push(span),
+        }
+    }
+
+    merged
+}
+
+#[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
+mod test_filter {
+    use super::Filter;
+
+    fn filter(line: &[&str], path: &[&str], value: &[&str], ignore_case: bool) -> Filter {
+        let strings = |p: &[&str]| p.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+
+        Filter::new(
+            &strings(line),
+            &strings(path),
+            &strings(value),
+            ignore_case,
+            false,
+        )
+        .unwrap()
+    }
+
+    const LINE: &str = ".spec.name => \"spec\"";
+    const PATH: std::ops::Range<usize> = 0..10;
+    const VALUE: std::ops::Range<usize> = 14..20;
+
+    #[test]
+    fn test_no_patterns() {
+        assert_eq!(
+            filter(&[], &[], &[], false).find(LINE, PATH, VALUE),
+            Some(vec![])
+        );
+    }
+
+    #[test]
+    fn test_line_patterns() {
+        let f = filter(&["^\\.spec", "name"], &[], &[], false);
+        assert_eq!(f.find(LINE, PATH, VALUE), Some(vec![0..5, 6..10]));
+
+        let f = filter(&["^spec"], &[], &[], false);
+        assert_eq!(f.find(LINE, PATH, VALUE), None);
+    }
+
+    #[test]
+    fn test_path_and_value_patterns() {
+        let f = filter(&[], &["spec"], &[], false);
+        assert_eq!(f.find(LINE, PATH, VALUE), Some(vec![1..5]));
+
+        let f = filter(&[], &[], &["spec"], false);
+        assert_eq!(f.find(LINE, PATH, VALUE), Some(vec![15..19]));
+
+        let f = filter(&[], &["spec"], &["^\"name"], false);
+        assert_eq!(f.find(LINE, PATH, VALUE), None);
+
+        let f = filter(&[], &[], &["name", "^\"spec\"$"], false);
+        assert_eq!(f.find(LINE, PATH, VALUE), Some(vec![14..20]));
+    }
+
+    #[test]
+    fn test_overlapping_matches_are_merged() {
+        let f = filter(&["spec", r"c\.n"], &[r"\.spec"], &[], false);
+        assert_eq!(f.find(LINE, PATH, VALUE), Some(vec![0..7, 15..19]));
+    }
+
+    #[test]
+    fn test_ignore_case() {
+        assert_eq!(
+            filter(&["SPEC"], &[], &[], false).find(LINE, PATH, VALUE),
+            None
+        );
+        assert_eq!(
+            filter(&["SPEC"], &[], &[], true).find(LINE, PATH, VALUE),
+            Some(vec![1..5, 15..19])
+        );
+    }
+
+    #[test]
+    fn test_invert() {
+        let f = Filter::new(&["name".to_string()], &[], &[], false, true).unwrap();
+        assert_eq!(f.find(LINE, PATH, VALUE), None);
+        assert_eq!(f.find(".spec => 1", 0..5, 9..10), Some(vec![]));
+    }
+
+    #[test]
+    fn test_empty_matches() {
+        let f = filter(&[], &[], &["^"], false);
+        assert_eq!(f.find(LINE, PATH, VALUE), Some(vec![]));
+    }
+}
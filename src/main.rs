@@ -3,36 +3,88 @@ extern crate lazy_static;
 
 use std::ffi::OsStr;
 use std::fs::File;
-use std::io::{self, stdin, BufReader, Read, Write};
-use std::path::Path;
+use std::io::{self, stdin, BufReader, BufWriter, Cursor, Read, Write};
+use std::process;
 use std::str;
 
-use clap::{CommandFactory, Parser, ValueEnum};
+use clap::{ArgGroup, CommandFactory, Parser, ValueEnum};
 use clap_complete::{generate, Shell};
+use document::Node;
 use error::Result;
+use filter::Filter;
+use path::{get_dialect, Identifiers, Path, Segment};
 use regex::Regex;
-use serde_json::Value;
-use value_writer::{get_writer, print_value};
+use select::Selector;
+use serde::de::IgnoredAny;
+use stream::{parse_json, parse_json_lines, stream_json};
+use unflatten::{unflatten, write_document};
+use value_writer::{get_writer, print_value, PrintOptions, ValueWriter, WriterOptions};
+use yaml::LoadOptions;
 
+mod document;
 mod error;
+mod filter;
+mod path;
+mod select;
+mod stream;
+mod unflatten;
 mod value_writer;
+mod yaml;
 
 #[derive(Debug, PartialEq, Eq)]
 enum InputDataType {
     Json,
+    JsonLines,
     Yaml,
     Toml,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 enum CmdDataType {
     Auto,
     Json,
+    Jsonl,
     Yaml,
     Toml,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CmdOutputType {
+    Json,
+    Yaml,
+    Toml,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CmdPathStyle {
+    /// jq filters, e.g. .foo."first name"[0]
+    Jq,
+    /// JSONPath expressions, e.g. $.foo['first name'][0]
+    Jsonpath,
+    /// JSON Pointers, e.g. /foo/first name/0, quoted as JSON strings when they contain control
+    /// characters or ` =>`
+    Pointer,
+    /// Bracket notation, e.g. ["foo"]["first name"][0]
+    Bracket,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CmdSortKeys {
+    /// Byte order, e.g. item1, item10, item2
+    Lexicographic,
+    /// Numbers within keys are compared by value, e.g. item1, item2, item10
+    Natural,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CmdYamlAliases {
+    /// Print the value of the anchor again at each alias
+    Expand,
+    /// Print each alias once, with the path of its anchor, e.g. *foo (see .foo)
+    Reference,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 enum CmdColor {
     Auto,
     Always,
@@ -41,9 +93,14 @@ enum CmdColor {
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
+#[clap(group(
+    ArgGroup::new("patterns")
+        .multiple(true)
+        .args(["pattern", "path_pattern", "value_pattern"])
+))]
 struct Args {
-    #[clap(help = "File to read. Use '-' for stdin.", default_value = "-")]
-    file: String,
+    #[clap(help = "Files to read. Use '-' for stdin.", default_value = "-")]
+    files: Vec<String>,
 
     #[clap(
         short = 't',
@@ -63,78 +120,502 @@ struct Args {
     )]
     color: CmdColor,
 
-    #[clap(short, long, help = "Apply a regex search on the output")]
-    pattern: Option<String>,
+    #[clap(
+        long,
+        value_name = "FIELDS",
+        help = "Prefix each document of a multi-document input with the given fields instead of its index (e.g. 'kind/metadata.name')"
+    )]
+    document_id: Option<String>,
+
+    #[clap(
+        short,
+        long,
+        value_name = "REGEX",
+        help = "Only print the lines that match a regex. Can be repeated to match any of them"
+    )]
+    pattern: Vec<String>,
+
+    #[clap(
+        long,
+        value_name = "REGEX",
+        help = "Only print the values whose path matches a regex. Can be repeated to match any of them"
+    )]
+    path_pattern: Vec<String>,
+
+    #[clap(
+        long,
+        value_name = "REGEX",
+        help = "Only print the values that match a regex, as printed. Can be repeated to match any of them"
+    )]
+    value_pattern: Vec<String>,
+
+    #[clap(short, long, help = "Match the patterns without regard to case")]
+    ignore_case: bool,
+
+    #[clap(
+        short = 'v',
+        long,
+        help = "Only print the lines that don't match the patterns",
+        requires = "patterns"
+    )]
+    invert_match: bool,
+
+    #[clap(
+        short,
+        long,
+        help = "Print nothing, only exit with status 0 if a value is selected and 1 otherwise"
+    )]
+    quiet: bool,
+
+    #[clap(
+        long,
+        help = "Print the number of selected values instead of the values",
+        conflicts_with = "quiet"
+    )]
+    count: bool,
+
+    #[clap(
+        long,
+        help = "Don't prefix the lines with file names when several files are given"
+    )]
+    no_filename: bool,
+
+    #[clap(
+        short,
+        long,
+        value_name = "NUM",
+        help = "Stop after NUM selected values"
+    )]
+    max_count: Option<usize>,
+
+    #[clap(
+        long,
+        help = "Print JSON and JSON Lines values as they are parsed, in input order, without loading whole documents in memory",
+        conflicts_with_all = ["document_id", "unflatten"]
+    )]
+    stream: bool,
+
+    #[clap(
+        long,
+        help = "Rebuild a document from the output of json-struct instead of flattening it"
+    )]
+    unflatten: bool,
+
+    #[clap(
+        long,
+        value_enum,
+        help = "Syntax of the printed paths, or of the paths read when using --unflatten",
+        default_value = "jq",
+        conflicts_with = "gron"
+    )]
+    path_style: CmdPathStyle,
+
+    #[clap(
+        long,
+        value_enum,
+        value_name = "ORDER",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "lexicographic",
+        help = "Sort object keys instead of keeping the order of the input",
+        conflicts_with = "stream"
+    )]
+    sort_keys: Option<CmdSortKeys>,
+
+    #[clap(
+        long,
+        value_name = "PATH",
+        help = "Only print the values under a path, with `*` for any key or index and `..` for any descendant, e.g. `.spec.containers[*].image` or `..password`. Can be repeated to print any of them",
+        conflicts_with = "stream"
+    )]
+    select: Vec<String>,
+
+    #[clap(
+        long,
+        value_name = "PATH",
+        help = "Leave out the values under a path, with the same wildcards as --select. Can be repeated to leave out any of them",
+        conflicts_with = "stream"
+    )]
+    exclude: Vec<String>,
+
+    #[clap(
+        long,
+        value_name = "FILE",
+        help = "Read paths to leave out from a file, one per line. Empty lines and lines starting with # are ignored",
+        conflicts_with = "stream"
+    )]
+    exclude_from: Option<String>,
+
+    #[clap(
+        long,
+        help = "Print a line in place of each excluded value, with the number of values it has",
+        conflicts_with = "gron"
+    )]
+    mark_excluded: bool,
+
+    #[clap(
+        long,
+        value_name = "N",
+        help = "Print the objects and arrays at depth N on a single line, as compact JSON, instead of their values",
+        conflicts_with = "stream"
+    )]
+    max_depth: Option<usize>,
+
+    #[clap(
+        long,
+        help = "Print the objects and arrays collapsed by --max-depth as their number of keys or items",
+        requires = "max_depth",
+        conflicts_with = "gron"
+    )]
+    summarize: bool,
+
+    #[clap(
+        long,
+        help = "Print gron-compatible assignments, or read them when using --unflatten"
+    )]
+    gron: bool,
+
+    #[clap(
+        long,
+        help = "Only print ASCII keys without quotes in JSONPath and gron paths. jq paths always do"
+    )]
+    ascii_keys: bool,
+
+    #[clap(
+        long,
+        help = "Print numbers from their value, e.g. 1000.0 for 1e3, rather than as written in the input"
+    )]
+    normalize_numbers: bool,
+
+    #[clap(
+        long,
+        help = "Print strings as they are, without quotes or escape sequences",
+        conflicts_with_all = ["gron", "unflatten"]
+    )]
+    raw_strings: bool,
+
+    #[clap(
+        long,
+        value_enum,
+        value_name = "MODE",
+        help = "How to print the aliases of YAML anchors",
+        default_value = "expand"
+    )]
+    yaml_aliases: CmdYamlAliases,
+
+    #[clap(
+        long,
+        help = "Mark the values that YAML mappings get from merge keys (<<)"
+    )]
+    mark_merged: bool,
+
+    #[clap(
+        short,
+        long = "output-type",
+        value_enum,
+        help = "Output data type of the rebuilt document when using --unflatten",
+        default_value = "json"
+    )]
+    output_type: CmdOutputType,
+
+    #[clap(long, help = "Print diagnostic information to stderr")]
+    verbose: bool,
 
     #[clap(long, value_enum, help = "Generate completion for a shell")]
     completion: Option<Shell>,
 }
 
-fn get_reader(filename: &str) -> Box<dyn Read> {
+fn get_reader(filename: &str) -> Result<Box<dyn Read>> {
     if filename == "-" {
-        Box::new(stdin())
+        Ok(Box::new(stdin()))
     } else {
-        let f = File::open(filename).expect("Error reading file");
-        Box::new(BufReader::new(f))
+        let f = File::open(filename)?;
+        Ok(Box::new(BufReader::new(f)))
     }
 }
 
+/// Reads the paths of a file, one per line, ignoring empty lines and comments.
+fn read_paths(filename: &str) -> Result<Vec<String>> {
+    let mut content = String::new();
+    get_reader(filename)?.read_to_string(&mut content)?;
+
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
 fn get_extension_from_filename(filename: &str) -> Option<&str> {
-    Path::new(filename).extension().and_then(OsStr::to_str)
+    std::path::Path::new(filename)
+        .extension()
+        .and_then(OsStr::to_str)
 }
 
-fn detect_data_type(filename: &str, data_type: CmdDataType) -> InputDataType {
+/// Detects the input data type from the command line or the file extension. Returns `None` if
+/// neither is conclusive, in which case the content has to be inspected.
+fn detect_data_type(filename: &str, data_type: CmdDataType) -> Option<InputDataType> {
     match data_type {
-        CmdDataType::Json => InputDataType::Json,
-        CmdDataType::Yaml => InputDataType::Yaml,
-        CmdDataType::Toml => InputDataType::Toml,
-        _ => match get_extension_from_filename(filename) {
-            Some("json") => InputDataType::Json,
-            Some("yaml") => InputDataType::Yaml,
-            Some("yml") => InputDataType::Yaml,
-            Some("toml") => InputDataType::Toml,
-            _ => InputDataType::Json,
+        CmdDataType::Json => Some(InputDataType::Json),
+        CmdDataType::Jsonl => Some(InputDataType::JsonLines),
+        CmdDataType::Yaml => Some(InputDataType::Yaml),
+        CmdDataType::Toml => Some(InputDataType::Toml),
+        CmdDataType::Auto => match get_extension_from_filename(filename) {
+            Some("json") => Some(InputDataType::Json),
+            Some("jsonl") => Some(InputDataType::JsonLines),
+            Some("ndjson") => Some(InputDataType::JsonLines),
+            Some("yaml") => Some(InputDataType::Yaml),
+            Some("yml") => Some(InputDataType::Yaml),
+            Some("toml") => Some(InputDataType::Toml),
+            _ => None,
         },
     }
 }
 
-fn parse_input_data(filename: &str, data_type: InputDataType) -> Result<Value> {
-    let mut rd = get_reader(filename);
+lazy_static! {
+    static ref TOML_TABLE_HEADER: Regex =
+        Regex::new(r#"^\[\[?\s*[\w\-."' ]+\]\]?\s*(#.*)?$"#).unwrap();
+    static ref TOML_KEY_VALUE: Regex = Regex::new(r#"^[\w\-."' ]+=(\s|$)"#).unwrap();
+}
+
+fn is_json_container(line: &str) -> bool {
+    matches!(line.parse::<Node>(), Ok(Node::Object(_) | Node::Array(_)))
+}
+
+/// Whether the text starts with a JSON container whose first key or item is written as JSON,
+/// unlike YAML flow collections such as `{foo: bar}` or `[a, b]`.
+fn starts_like_json(text: &str) -> bool {
+    let text = text.trim_start();
+
+    if let Some(rest) = text.strip_prefix('{') {
+        rest.trim_start().starts_with(['"', '}'])
+    } else if let Some(rest) = text.strip_prefix('[') {
+        let rest = rest.trim_start();
+        rest.starts_with(['"', '{', '[', ']', '-'])
+            || rest.starts_with(|c: char| c.is_ascii_digit())
+            || ["true", "false", "null"].iter().any(|word| {
+                rest.strip_prefix(word)
+                    .is_some_and(|r| !r.starts_with(|c: char| c.is_alphanumeric()))
+            })
+    } else {
+        false
+    }
+}
+
+/// Detects the input data type from the first bytes of the input. If `complete` is true, `head`
+/// is the whole input and a trial parse is used to settle ambiguous cases.
+fn detect_data_type_from_content(head: &[u8], complete: bool) -> InputDataType {
+    let text = match str::from_utf8(head) {
+        Ok(text) => text,
+        // The head may end in the middle of a multi-byte character.
+        Err(e) => str::from_utf8(&head[..e.valid_up_to()]).unwrap(),
+    };
+    let text = text.trim_start_matches('\u{feff}');
+
+    if text.trim().is_empty() {
+        return InputDataType::Json;
+    }
+
+    if complete && serde_json::from_str::<IgnoredAny>(text).is_ok() {
+        return InputDataType::Json;
+    }
+
+    // The last line of a truncated head is partial, it can't be used.
+    let line_count = if complete {
+        text.lines().count()
+    } else {
+        text.lines().count() - 1
+    };
+    let lines: Vec<&str> = text
+        .lines()
+        .take(line_count)
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+
+    if lines.len() > 1 && lines.iter().take(2).all(|l| is_json_container(l)) {
+        return InputDataType::JsonLines;
+    }
+
+    let first_line = lines
+        .iter()
+        .find(|l| !l.starts_with('#'))
+        .copied()
+        .unwrap_or_else(|| text.trim());
+
+    if complete {
+        if toml::from_str::<toml::Table>(text).is_ok() {
+            return InputDataType::Toml;
+        }
+        // Invalid JSON is reported as such, rather than read as YAML, which accepts most of it.
+        if starts_like_json(text) && !TOML_TABLE_HEADER.is_match(first_line) {
+            return InputDataType::Json;
+        }
+        if yaml::load_documents(text, LoadOptions::default()).is_ok() {
+            return InputDataType::Yaml;
+        }
+    }
+
+    if TOML_TABLE_HEADER.is_match(first_line) || TOML_KEY_VALUE.is_match(first_line) {
+        InputDataType::Toml
+    } else if first_line.starts_with('{') || first_line.starts_with('[') {
+        InputDataType::Json
+    } else {
+        InputDataType::Yaml
+    }
+}
+
+/// Number of bytes inspected when detecting the data type from the content.
+const SNIFF_LENGTH: usize = 64 * 1024;
+
+/// Peeks at the beginning of the input to detect its data type, returning a reader that still
+/// yields the whole input.
+fn sniff_data_type(mut rd: Box<dyn Read>) -> Result<(InputDataType, Box<dyn Read>)> {
+    let mut head = Vec::with_capacity(SNIFF_LENGTH);
+    (&mut rd).take(SNIFF_LENGTH as u64).read_to_end(&mut head)?;
+
+    let data_type = detect_data_type_from_content(&head, head.len() < SNIFF_LENGTH);
+
+    Ok((data_type, Box::new(Cursor::new(head).chain(rd))))
+}
+
+/// A stream of parsed documents. Documents of multi-document inputs come with the segment
+/// identifying them, which prefixes the paths of their values.
+type Documents = Box<dyn Iterator<Item = Result<(Option<Segment>, Node)>>>;
+
+fn single_document(value: Node) -> Documents {
+    Box::new(std::iter::once(Ok((None, value))))
+}
+
+/// Builds the identity of a document from `/`-separated dotted field paths, e.g.
+/// `kind/metadata.name` gives `Deployment/my-app`. Returns `None` if any field is missing or
+/// isn't a scalar.
+fn get_document_id(value: &Node, document_id: &str) -> Option<String> {
+    document_id
+        .split('/')
+        .map(|field| {
+            field
+                .split('.')
+                .filter(|k| !k.is_empty())
+                .try_fold(value, |v, k| v.get(k))
+                .and_then(Node::scalar_to_string)
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|fields| fields.join("/"))
+}
+
+fn get_document_segment(index: usize, value: &Node, document_id: Option<&str>) -> Segment {
+    match document_id.and_then(|id| get_document_id(value, id)) {
+        Some(id) => Segment::Key(id),
+        None => Segment::Index(index),
+    }
+}
+
+fn parse_yaml_documents(
+    mut rd: Box<dyn Read>,
+    document_id: Option<String>,
+    options: LoadOptions,
+) -> Result<Documents> {
+    let mut buf = String::new();
+    rd.read_to_string(&mut buf)?;
+    let mut documents = yaml::load_documents(&buf, options)?;
+
+    // Streams with separators around a single document (`---`) are common, skip empty ones.
+    if documents.len() > 1 {
+        documents.retain(|d| !d.is_null());
+    }
+
+    if documents.len() == 1 && document_id.is_none() {
+        return Ok(single_document(documents.remove(0)));
+    }
 
-    let val = match data_type {
-        InputDataType::Json => serde_json::from_reader(rd)?,
-        InputDataType::Yaml => serde_yaml::from_reader(rd)?,
+    let documents = documents
+        .into_iter()
+        .enumerate()
+        .map(move |(i, v)| Ok((Some(get_document_segment(i, &v, document_id.as_deref())), v)));
+
+    Ok(Box::new(documents))
+}
+
+fn parse_input_data(
+    mut rd: Box<dyn Read>,
+    data_type: InputDataType,
+    document_id: Option<String>,
+    yaml_options: LoadOptions,
+) -> Result<Documents> {
+    let documents = match data_type {
+        InputDataType::Json => single_document(parse_json(BufReader::new(rd))?),
+        InputDataType::JsonLines => {
+            // Records are parsed lazily so that output starts before the whole input is read.
+            let records = parse_json_lines(BufReader::new(rd))
+                .enumerate()
+                .map(move |(i, v)| {
+                    let v = v?;
+                    Ok((Some(get_document_segment(i, &v, document_id.as_deref())), v))
+                });
+
+            Box::new(records)
+        }
+        InputDataType::Yaml => parse_yaml_documents(rd, document_id, yaml_options)?,
         InputDataType::Toml => {
             let mut buf: String = Default::default();
             rd.read_to_string(&mut buf)?;
-            toml::from_str(&buf)?
+            single_document(document::from_toml(&buf)?)
         }
     };
 
-    Ok(val)
+    Ok(documents)
 }
 
-struct GrepWriter<'a> {
-    sink: &'a mut dyn Write,
-    regex: Regex,
-}
-
-impl std::io::Write for GrepWriter<'_> {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        if self.regex.is_match(str::from_utf8(buf).unwrap()) {
-            return self.sink.write(buf);
+/// Prints the values of a file, up to the point where the writer is done.
+fn print_file(
+    file: &str,
+    args: &Args,
+    print_options: &PrintOptions,
+    value_writer: &mut dyn ValueWriter,
+) -> Result<()> {
+    let rd = get_reader(file)?;
+    let (data_type, rd) = match detect_data_type(file, args.data_type) {
+        Some(data_type) => (data_type, rd),
+        None => {
+            let (data_type, rd) = sniff_data_type(rd)?;
+            if args.verbose {
+                eprintln!("Detected {:?} input from content", data_type);
+            }
+            (data_type, rd)
         }
-        Ok(buf.len())
+    };
+
+    // YAML and TOML documents can't be streamed, and are loaded as usual.
+    if args.stream && matches!(data_type, InputDataType::Json | InputDataType::JsonLines) {
+        let json_lines = data_type == InputDataType::JsonLines;
+        return stream_json(BufReader::new(rd), json_lines, value_writer);
     }
 
-    fn flush(&mut self) -> io::Result<()> {
-        self.sink.flush()
+    let yaml_options = LoadOptions {
+        keep_aliases: args.yaml_aliases == CmdYamlAliases::Reference,
+        mark_merged: args.mark_merged,
+    };
+    let documents = parse_input_data(rd, data_type, args.document_id.clone(), yaml_options)?;
+
+    for document in documents {
+        let (segment, data) = document?;
+
+        print_value(&mut Path::new(segment), data, value_writer, print_options)?;
+        value_writer.flush()?;
+
+        if value_writer.is_done() {
+            break;
+        }
     }
-}
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+    Ok(())
+}
 
+/// Runs the command, returning the exit status: like grep, 0 when a value was selected, 1 when
+/// none was, and 2 when a file couldn't be read.
+fn run(args: Args) -> Result<i32> {
     if let Some(shell) = args.completion {
         generate(
             shell,
@@ -142,28 +623,125 @@ fn main() -> Result<()> {
             "json-struct",
             &mut io::stdout(),
         );
-        return Ok(());
+        return Ok(0);
     }
 
-    let data_type = detect_data_type(&args.file, args.data_type);
-    let data = parse_input_data(&args.file, data_type)?;
+    let identifiers = args.ascii_keys.then_some(Identifiers::Ascii);
+    let dialect = get_dialect(args.path_style, identifiers);
 
-    let regex = args
-        .pattern
-        .map(|p| Regex::new(&p).expect("Invalid regex pattern"));
+    if args.unflatten {
+        if args.files.len() > 1 {
+            Args::command()
+                .error(
+                    clap::error::ErrorKind::TooManyValues,
+                    "--unflatten reads a single file",
+                )
+                .exit();
+        }
 
-    let mut stdout = io::stdout();
+        let rd = get_reader(&args.files[0])?;
+        let document = unflatten(BufReader::new(rd), dialect, args.gron)?;
+        write_document(&mut io::stdout(), &document, args.output_type)?;
+        return Ok(0);
+    }
 
-    let mut output_writer: Box<dyn Write> = match regex {
-        Some(r) => Box::new(GrepWriter {
-            sink: &mut stdout,
-            regex: r,
-        }),
-        None => Box::new(stdout),
+    let filter = Filter::new(
+        &args.pattern,
+        &args.path_pattern,
+        &args.value_pattern,
+        args.ignore_case,
+        args.invert_match,
+    )?;
+    let mut exclude = args.exclude.clone();
+    if let Some(file) = &args.exclude_from {
+        exclude.extend(read_paths(file)?);
+    }
+    let print_options = PrintOptions {
+        sort_keys: args.sort_keys,
+        selector: Selector::new(&args.select, &exclude)?,
+        mark_excluded: args.mark_excluded,
+        max_depth: args.max_depth,
     };
-    let mut value_writer = get_writer(&mut output_writer, args.color);
 
-    print_value(".", data, &mut (*value_writer))
+    // The output is flushed after each document rather than after each line.
+    let mut stdout = BufWriter::new(io::stdout().lock());
+    let mut value_writer = get_writer(
+        &mut stdout,
+        args.color,
+        args.gron,
+        dialect,
+        identifiers,
+        WriterOptions {
+            normalize_numbers: args.normalize_numbers,
+            raw_strings: args.raw_strings,
+            filter,
+            quiet: args.quiet || args.count,
+            // The first value is enough to know the exit status.
+            max_count: if args.quiet { Some(1) } else { args.max_count },
+            summarize: args.summarize,
+        },
+    );
+
+    // Like grep, the lines are prefixed with file names when there are several files, and a
+    // file that can't be read is reported without stopping the others.
+    let with_filename = args.files.len() > 1 && !args.no_filename;
+    let mut counts = Vec::new();
+    let mut failed = false;
+    for file in &args.files {
+        let name = if file == "-" {
+            "(standard input)"
+        } else {
+            file
+        };
+        value_writer.set_file(with_filename.then(|| name.to_string()));
+
+        let matches = value_writer.matches();
+        if let Err(e) = print_file(file, &args, &print_options, &mut *value_writer) {
+            value_writer.flush()?;
+            eprintln!("Error: {}: {}", name, e);
+            failed = true;
+            continue;
+        }
+        counts.push((name, value_writer.matches() - matches));
+
+        if value_writer.is_done() {
+            break;
+        }
+    }
+
+    let matches = value_writer.matches();
+    drop(value_writer);
+
+    if args.count {
+        for (name, count) in counts {
+            if with_filename {
+                writeln!(stdout, "{}:{}", name, count)?;
+            } else {
+                writeln!(stdout, "{}", count)?;
+            }
+        }
+    }
+
+    Ok(if failed {
+        2
+    } else if matches > 0 {
+        0
+    } else {
+        1
+    })
+}
+
+fn main() {
+    let args = Args::parse();
+
+    // Like grep, the exit status is 1 when no value was selected, and 2 on errors.
+    match run(args) {
+        Ok(status) => process::exit(status),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(2);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -199,6 +777,63 @@ mod test_get_extension_from_filename {
     }
 }
 
+#[cfg(test)]
+mod test_get_document_segment {
+    use serde_json::json;
+
+    use super::get_document_segment;
+    use crate::document::Node;
+    use crate::path::Segment;
+
+    #[test]
+    fn test_no_document_id_returns_the_index() {
+        assert_eq!(
+            get_document_segment(3, &Node::from(json!({"kind": "Pod"})), None),
+            Segment::Index(3)
+        );
+    }
+
+    #[test]
+    fn test_document_id_returns_the_fields() {
+        let value = Node::from(json!({"kind": "Deployment", "metadata": {"name": "my-app"}}));
+
+        assert_eq!(
+            get_document_segment(3, &value, Some("kind/metadata.name")),
+            Segment::Key("Deployment/my-app".to_string())
+        );
+    }
+
+    #[test]
+    fn test_document_id_with_non_string_field() {
+        let value = Node::from(json!({"id": 42}));
+
+        assert_eq!(
+            get_document_segment(0, &value, Some("id")),
+            Segment::Key("42".to_string())
+        );
+    }
+
+    #[test]
+    fn test_document_id_with_container_field_returns_the_index() {
+        let value = Node::from(json!({"metadata": {"labels": {}}}));
+
+        assert_eq!(
+            get_document_segment(3, &value, Some("metadata.labels")),
+            Segment::Index(3)
+        );
+    }
+
+    #[test]
+    fn test_document_id_with_missing_field_returns_the_index() {
+        let value = Node::from(json!({"kind": "Namespace"}));
+
+        assert_eq!(
+            get_document_segment(3, &value, Some("kind/metadata.name")),
+            Segment::Index(3)
+        );
+    }
+}
+
 #[cfg(test)]
 mod test_detect_data_type {
     use super::{detect_data_type, InputDataType};
@@ -208,7 +843,15 @@ mod test_detect_data_type {
     fn test_forcing_json_returns_json_type() {
         assert_eq!(
             detect_data_type("file.yaml", CmdDataType::Json),
-            InputDataType::Json
+            Some(InputDataType::Json)
+        );
+    }
+
+    #[test]
+    fn test_forcing_jsonl_returns_json_lines_type() {
+        assert_eq!(
+            detect_data_type("file.json", CmdDataType::Jsonl),
+            Some(InputDataType::JsonLines)
         );
     }
 
@@ -216,7 +859,7 @@ mod test_detect_data_type {
     fn test_forcing_yaml_returns_yaml_type() {
         assert_eq!(
             detect_data_type("file.json", CmdDataType::Yaml),
-            InputDataType::Yaml
+            Some(InputDataType::Yaml)
         );
     }
 
@@ -224,7 +867,7 @@ mod test_detect_data_type {
     fn test_forcing_toml_returns_toml_type() {
         assert_eq!(
             detect_data_type("file.json", CmdDataType::Toml),
-            InputDataType::Toml
+            Some(InputDataType::Toml)
         );
     }
 
@@ -232,7 +875,23 @@ mod test_detect_data_type {
     fn test_file_with_json_extension_returns_json_type() {
         assert_eq!(
             detect_data_type("file.json", CmdDataType::Auto),
-            InputDataType::Json
+            Some(InputDataType::Json)
+        );
+    }
+
+    #[test]
+    fn test_file_with_jsonl_extension_returns_json_lines_type() {
+        assert_eq!(
+            detect_data_type("file.jsonl", CmdDataType::Auto),
+            Some(InputDataType::JsonLines)
+        );
+    }
+
+    #[test]
+    fn test_file_with_ndjson_extension_returns_json_lines_type() {
+        assert_eq!(
+            detect_data_type("file.ndjson", CmdDataType::Auto),
+            Some(InputDataType::JsonLines)
         );
     }
 
@@ -240,7 +899,7 @@ mod test_detect_data_type {
     fn test_file_with_yaml_extension_returns_yaml_type() {
         assert_eq!(
             detect_data_type("file.yaml", CmdDataType::Auto),
-            InputDataType::Yaml
+            Some(InputDataType::Yaml)
         );
     }
 
@@ -248,7 +907,7 @@ mod test_detect_data_type {
     fn test_file_with_yml_extension_returns_yaml_type() {
         assert_eq!(
             detect_data_type("file.yml", CmdDataType::Auto),
-            InputDataType::Yaml
+            Some(InputDataType::Yaml)
         );
     }
 
@@ -256,23 +915,144 @@ mod test_detect_data_type {
     fn test_file_with_toml_extension_returns_toml_type() {
         assert_eq!(
             detect_data_type("file.toml", CmdDataType::Auto),
-            InputDataType::Toml
+            Some(InputDataType::Toml)
         );
     }
 
     #[test]
-    fn test_file_with_any_unknown_extension_returns_json_type() {
+    fn test_file_with_any_unknown_extension_returns_none() {
+        assert_eq!(detect_data_type("file.foo", CmdDataType::Auto), None);
+    }
+
+    #[test]
+    fn test_file_with_no_extension_returns_none() {
+        assert_eq!(detect_data_type("-", CmdDataType::Auto), None);
+    }
+}
+
+#[cfg(test)]
+mod test_detect_data_type_from_content {
+    use super::{detect_data_type_from_content, InputDataType};
+
+    #[test]
+    fn test_empty_content_returns_json_type() {
         assert_eq!(
-            detect_data_type("file.foo", CmdDataType::Auto),
+            detect_data_type_from_content(b"  \n", true),
             InputDataType::Json
         );
     }
 
     #[test]
-    fn test_file_with_no_extension_returns_json_type() {
+    fn test_json_content_returns_json_type() {
         assert_eq!(
-            detect_data_type("-", CmdDataType::Auto),
+            detect_data_type_from_content(include_bytes!("../tests/example.json"), true),
             InputDataType::Json
         );
     }
+
+    #[test]
+    fn test_json_scalar_returns_json_type() {
+        assert_eq!(
+            detect_data_type_from_content(b"\"foo\"", true),
+            InputDataType::Json
+        );
+    }
+
+    #[test]
+    fn test_truncated_json_content_returns_json_type() {
+        assert_eq!(
+            detect_data_type_from_content(b"{\n  \"foo\": [1, 2", false),
+            InputDataType::Json
+        );
+    }
+
+    #[test]
+    fn test_json_lines_content_returns_json_lines_type() {
+        assert_eq!(
+            detect_data_type_from_content(include_bytes!("../tests/example.jsonl"), true),
+            InputDataType::JsonLines
+        );
+    }
+
+    #[test]
+    fn test_truncated_json_lines_content_returns_json_lines_type() {
+        assert_eq!(
+            detect_data_type_from_content(b"{\"a\": 1}\n{\"a\": 2}\n{\"a\"", false),
+            InputDataType::JsonLines
+        );
+    }
+
+    #[test]
+    fn test_yaml_content_returns_yaml_type() {
+        assert_eq!(
+            detect_data_type_from_content(include_bytes!("../tests/example.yaml"), true),
+            InputDataType::Yaml
+        );
+    }
+
+    #[test]
+    fn test_yaml_flow_mapping_returns_yaml_type() {
+        assert_eq!(
+            detect_data_type_from_content(b"{foo: bar}", true),
+            InputDataType::Yaml
+        );
+    }
+
+    #[test]
+    fn test_invalid_json_content_returns_json_type() {
+        for content in [
+            &b"{\"a\": 1,}"[..],
+            b"{\"a\": 01}",
+            b"[1, 2,]",
+            b"[\"a\", b]",
+            b"[true, yes]",
+        ] {
+            assert_eq!(
+                detect_data_type_from_content(content, true),
+                InputDataType::Json,
+                "{}",
+                String::from_utf8_lossy(content)
+            );
+        }
+    }
+
+    #[test]
+    fn test_yaml_flow_sequence_returns_yaml_type() {
+        assert_eq!(
+            detect_data_type_from_content(b"[a, b]", true),
+            InputDataType::Yaml
+        );
+        assert_eq!(
+            detect_data_type_from_content(b"[truer, 1]", true),
+            InputDataType::Yaml
+        );
+    }
+
+    #[test]
+    fn test_truncated_yaml_content_returns_yaml_type() {
+        assert_eq!(
+            detect_data_type_from_content(b"# comment\nfoo:\n  - bar\n  - b", false),
+            InputDataType::Yaml
+        );
+    }
+
+    #[test]
+    fn test_toml_content_returns_toml_type() {
+        assert_eq!(
+            detect_data_type_from_content(include_bytes!("../tests/example.toml"), true),
+            InputDataType::Toml
+        );
+    }
+
+    #[test]
+    fn test_truncated_toml_content_returns_toml_type() {
+        assert_eq!(
+            detect_data_type_from_content(b"# comment\ntitle = \"TOML\"\n[own", false),
+            InputDataType::Toml
+        );
+        assert_eq!(
+            detect_data_type_from_content(b"[servers.alpha]\nip = \"10.0", false),
+            InputDataType::Toml
+        );
+    }
 }
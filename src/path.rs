@@ -0,0 +1,1023 @@
+use std::fmt::{self, Display, Formatter, Write};
+
+use crate::document::Number;
+use crate::CmdPathStyle;
+
+/// Paths are parsed from the start of the input, with the remainder of the input returned
+/// alongside so that the caller can parse the rest of the line.
+pub(super) type ParseResult<'a, T> = std::result::Result<(T, &'a str), String>;
+
+/// A single element of a path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Segment {
+    Key(String),
+    /// A key that isn't a string, e.g. the `200` of a YAML mapping, as text. Dialects that only
+    /// have string keys print it like a string key.
+    OtherKey(String),
+    Index(usize),
+}
+
+/// The path of a value, from the root of its document. Values of multi-document inputs also
+/// carry the segment identifying their document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct Path {
+    document: Option<Segment>,
+    segments: Vec<Segment>,
+}
+
+impl Path {
+    pub(super) fn new(document: Option<Segment>) -> Self {
+        Self {
+            document,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Iterates over all the segments of the path, starting with the document segment.
+    pub(super) fn iter(&self) -> impl Iterator<Item = &Segment> {
+        self.document.iter().chain(self.segments.iter())
+    }
+
+    /// The segments of the path, without the document segment.
+    pub(super) fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    pub(super) fn push(&mut self, segment: Segment) {
+        self.segments.push(segment);
+    }
+
+    pub(super) fn pop(&mut self) -> Option<Segment> {
+        self.segments.pop()
+    }
+
+    /// Returns the path of other segments of the same document.
+    pub(super) fn with_segments(&self, segments: Vec<Segment>) -> Path {
+        Self {
+            document: self.document.clone(),
+            segments,
+        }
+    }
+
+    pub(super) fn display<'a>(&'a self, dialect: &'a dyn PathDialect) -> PathDisplay<'a> {
+        PathDisplay {
+            path: self,
+            dialect,
+        }
+    }
+}
+
+impl From<Vec<Segment>> for Path {
+    fn from(segments: Vec<Segment>) -> Self {
+        Self {
+            document: None,
+            segments,
+        }
+    }
+}
+
+pub(super) struct PathDisplay<'a> {
+    path: &'a Path,
+    dialect: &'a dyn PathDialect,
+}
+
+impl Display for PathDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.dialect.fmt(self.path, f)
+    }
+}
+
+/// A syntax in which paths are printed and parsed back.
+pub(super) trait PathDialect {
+    fn fmt(&self, path: &Path, f: &mut Formatter<'_>) -> fmt::Result;
+    fn parse<'a>(&self, s: &'a str) -> ParseResult<'a, Path>;
+}
+
+/// Returns the dialect of a path style. Without `identifiers`, each dialect prints as identifiers
+/// the keys that the matching tool reads: JSONPath reads Unicode identifiers, while jq only ever
+/// reads ASCII ones, whatever `identifiers` is.
+pub(super) fn get_dialect(
+    style: CmdPathStyle,
+    identifiers: Option<Identifiers>,
+) -> &'static dyn PathDialect {
+    match (style, identifiers) {
+        (CmdPathStyle::Jq, _) => &JqDialect,
+        (CmdPathStyle::Jsonpath, None | Some(Identifiers::Unicode)) => {
+            &JsonPathDialect(Identifiers::Unicode)
+        }
+        (CmdPathStyle::Jsonpath, Some(Identifiers::Ascii)) => &JsonPathDialect(Identifiers::Ascii),
+        (CmdPathStyle::Pointer, _) => &JsonPointerDialect,
+        (CmdPathStyle::Bracket, _) => &BracketDialect,
+    }
+}
+
+/// The keys that dialects print as identifiers, without quotes or brackets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Identifiers {
+    /// Unicode identifiers (XID_Start and XID_Continue), e.g. `libellé` or `名前`.
+    Unicode,
+    /// ASCII letters, digits and underscores only, for tools that don't accept other identifiers.
+    Ascii,
+}
+
+impl Identifiers {
+    fn is_start(self, c: char) -> bool {
+        match self {
+            Self::Unicode => c == '_' || unicode_ident::is_xid_start(c),
+            Self::Ascii => c == '_' || c.is_ascii_alphabetic(),
+        }
+    }
+
+    fn is_continue(self, c: char) -> bool {
+        match self {
+            Self::Unicode => unicode_ident::is_xid_continue(c),
+            Self::Ascii => c == '_' || c.is_ascii_alphanumeric(),
+        }
+    }
+
+    fn is_identifier(self, key: &str) -> bool {
+        let mut chars = key.chars();
+
+        matches!(chars.next(), Some(c) if self.is_start(c)) && chars.all(|c| self.is_continue(c))
+    }
+}
+
+/// Parses an array index up to the closing bracket, which is left in the input.
+pub(super) fn parse_index(s: &str) -> ParseResult<'_, usize> {
+    let end = s.find(']').ok_or("unterminated index")?;
+    let index = s[..end]
+        .parse()
+        .map_err(|_| format!("invalid index {:?}", &s[..end]))?;
+
+    Ok((index, &s[end..]))
+}
+
+/// Parses an unquoted key, which ends at the next separator.
+pub(super) fn parse_identifier(s: &str) -> ParseResult<'_, String> {
+    let end = s.find(['.', '[', ' ']).unwrap_or(s.len());
+
+    Ok((s[..end].to_string(), &s[end..]))
+}
+
+/// Parses a JSON encoded string at the start of the input.
+pub(super) fn parse_json_string(s: &str) -> ParseResult<'_, String> {
+    let mut escaped = false;
+
+    for (i, c) in s.char_indices().skip(1) {
+        match c {
+            '"' if !escaped => {
+                let value = serde_json::from_str(&s[..i + 1]).map_err(|e| e.to_string())?;
+                return Ok((value, &s[i + 1..]));
+            }
+            '\\' => escaped = !escaped,
+            _ => escaped = false,
+        }
+    }
+
+    Err("unterminated string".to_string())
+}
+
+/// Encodes a string as JSON. Unlike `serde_json`, DEL and the C1 control characters are escaped
+/// too, so that no terminal escape sequence makes it to the output.
+pub(super) fn to_json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+
+    out
+}
+
+/// `jq` paths, e.g. `.foo."first name"[0]`.
+pub(super) struct JqDialect;
+
+impl JqDialect {
+    /// Whether a key that isn't a string is printed without brackets, e.g. `.200` or `.true`.
+    /// These never look like string keys, which are quoted when they are numbers or keywords.
+    fn is_bare_other_key(&self, key: &str) -> bool {
+        key.chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+'))
+            && (key.parse::<Number>().is_ok() || matches!(key, "true" | "false" | "null"))
+            && escape_path_element(key) != key
+    }
+
+    /// Parses a bracketed key that isn't a string up to the closing bracket, which is left in the
+    /// input. The key may itself contain brackets, braces and strings, e.g. `[[a, "]"]]`.
+    fn parse_other_key(s: &str) -> ParseResult<'_, String> {
+        let mut depth = 0;
+        let mut in_string = false;
+        let mut escaped = false;
+
+        for (i, c) in s.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if in_string => escaped = true,
+                '"' => in_string = !in_string,
+                _ if in_string => {}
+                '[' | '{' => depth += 1,
+                '}' => depth -= 1,
+                ']' if depth == 0 => return Ok((s[..i].to_string(), &s[i..])),
+                ']' => depth -= 1,
+                _ => {}
+            }
+        }
+
+        Err("unterminated key".to_string())
+    }
+}
+
+impl PathDialect for JqDialect {
+    fn fmt(&self, path: &Path, f: &mut Formatter<'_>) -> fmt::Result {
+        match &path.document {
+            Some(Segment::Key(k) | Segment::OtherKey(k)) => write!(f, "[{}]", to_json_string(k))?,
+            Some(Segment::Index(i)) => write!(f, "[{}]", i)?,
+            None => f.write_str(".")?,
+        }
+
+        for (i, segment) in path.segments.iter().enumerate() {
+            match segment {
+                Segment::Key(k) => {
+                    // The root dot is shared with the first key.
+                    if i > 0 || path.document.is_some() {
+                        f.write_str(".")?;
+                    }
+                    f.write_str(&escape_path_element(k))?;
+                }
+                Segment::OtherKey(k) if self.is_bare_other_key(k) => {
+                    if i > 0 || path.document.is_some() {
+                        f.write_str(".")?;
+                    }
+                    f.write_str(k)?;
+                }
+                Segment::OtherKey(k) => write!(f, "[{}]", k)?,
+                Segment::Index(i) => write!(f, "[{}]", i)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse<'a>(&self, s: &'a str) -> ParseResult<'a, Path> {
+        let mut path = Path::default();
+        let mut rest = s;
+
+        loop {
+            if let Some(r) = rest.strip_prefix('.') {
+                if r.starts_with('"') {
+                    let (key, r) = parse_json_string(r)?;
+                    path.push(Segment::Key(key));
+                    rest = r;
+                } else {
+                    let (key, r) = parse_identifier(r)?;
+                    // A lone dot is the root.
+                    if self.is_bare_other_key(&key) {
+                        path.push(Segment::OtherKey(key));
+                    } else if !key.is_empty() {
+                        path.push(Segment::Key(key));
+                    }
+                    rest = r;
+                }
+            } else if let Some(r) = rest.strip_prefix('[') {
+                // Paths of multi-document inputs start with the document segment.
+                let is_document = rest.len() == s.len();
+
+                let (segment, r) = if r.starts_with('"') {
+                    let (key, r) = parse_json_string(r)?;
+                    (Segment::Key(key), r)
+                } else {
+                    let (key, r) = Self::parse_other_key(r)?;
+                    let segment = match key.parse() {
+                        Ok(index) if key.chars().all(|c| c.is_ascii_digit()) => {
+                            Segment::Index(index)
+                        }
+                        // Identifiers are keys written the wrong way, rather than keys that
+                        // aren't strings.
+                        _ if is_document
+                            || key.is_empty()
+                            || (escape_path_element(&key) == key
+                                && key.parse::<Number>().is_err()) =>
+                        {
+                            return Err(format!("invalid index {:?}", key))
+                        }
+                        _ => Segment::OtherKey(key),
+                    };
+                    (segment, r)
+                };
+                rest = r.strip_prefix(']').ok_or("expected ']'")?;
+
+                if is_document {
+                    path.document = Some(segment);
+                } else {
+                    path.push(segment);
+                }
+            } else if rest.len() == s.len() {
+                return Err("paths must start with '.' or '['".to_string());
+            } else {
+                return Ok((path, rest));
+            }
+        }
+    }
+}
+
+/// Quotes the keys that can't be written as a bare jq identifier, or that would read as a key
+/// that isn't a string, e.g. `true`.
+fn escape_path_element(p: &str) -> String {
+    if !Identifiers::Ascii.is_identifier(p) || matches!(p, "true" | "false" | "null") {
+        to_json_string(p)
+    } else {
+        p.to_string()
+    }
+}
+
+/// JSONPath expressions (RFC 9535), e.g. `$.foo['first name'][0]`.
+pub(super) struct JsonPathDialect(pub(super) Identifiers);
+
+impl JsonPathDialect {
+    /// Parses a single quoted key, with the escape sequences of RFC 9535.
+    fn parse_quoted_key(s: &str) -> ParseResult<'_, String> {
+        let mut key = String::new();
+        let mut chars = s.char_indices().skip(1);
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\'' => return Ok((key, &s[i + 1..])),
+                '\\' => match chars.next() {
+                    Some((_, 'b')) => key.push('\u{8}'),
+                    Some((_, 'f')) => key.push('\u{c}'),
+                    Some((_, 'n')) => key.push('\n'),
+                    Some((_, 'r')) => key.push('\r'),
+                    Some((_, 't')) => key.push('\t'),
+                    Some((j, 'u')) => {
+                        let code = s
+                            .get(j + 1..j + 5)
+                            .and_then(|h| u32::from_str_radix(h, 16).ok())
+                            .and_then(char::from_u32)
+                            .ok_or("invalid unicode escape")?;
+                        key.push(code);
+                        chars.nth(3);
+                    }
+                    Some((_, c @ ('\'' | '"' | '\\' | '/'))) => key.push(c),
+                    _ => return Err("invalid escape sequence".to_string()),
+                },
+                c => key.push(c),
+            }
+        }
+
+        Err("unterminated quoted key".to_string())
+    }
+}
+
+impl PathDialect for JsonPathDialect {
+    fn fmt(&self, path: &Path, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("$")?;
+
+        for segment in path.iter() {
+            match segment {
+                Segment::Key(k) | Segment::OtherKey(k) if self.0.is_identifier(k) => {
+                    write!(f, ".{}", k)?
+                }
+                Segment::Key(k) | Segment::OtherKey(k) => {
+                    f.write_str("['")?;
+                    for c in k.chars() {
+                        match c {
+                            '\'' => f.write_str("\\'")?,
+                            '\\' => f.write_str("\\\\")?,
+                            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+                            c => write!(f, "{}", c)?,
+                        }
+                    }
+                    f.write_str("']")?;
+                }
+                Segment::Index(i) => write!(f, "[{}]", i)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse<'a>(&self, s: &'a str) -> ParseResult<'a, Path> {
+        let mut path = Path::default();
+        let mut rest = s
+            .strip_prefix('$')
+            .ok_or("JSONPath expressions must start with '$'")?;
+
+        loop {
+            if let Some(r) = rest.strip_prefix('.') {
+                let (key, r) = parse_identifier(r)?;
+                if key.is_empty() {
+                    return Err("expected a key after '.'".to_string());
+                }
+                path.push(Segment::Key(key));
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix('[') {
+                let (segment, r) = if r.starts_with('\'') {
+                    let (key, r) = Self::parse_quoted_key(r)?;
+                    (Segment::Key(key), r)
+                } else {
+                    let (index, r) = parse_index(r)?;
+                    (Segment::Index(index), r)
+                };
+                path.push(segment);
+                rest = r.strip_prefix(']').ok_or("expected ']'")?;
+            } else {
+                return Ok((path, rest));
+            }
+        }
+    }
+}
+
+/// JSON Pointers (RFC 6901), e.g. `/foo/first name/0`. A pointer that contains control
+/// characters or the ` => ` separator is quoted as a JSON string, e.g. `"/a =\u003e b"`.
+pub(super) struct JsonPointerDialect;
+
+impl PathDialect for JsonPointerDialect {
+    fn fmt(&self, path: &Path, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut pointer = String::new();
+        for segment in path.iter() {
+            match segment {
+                Segment::Key(k) | Segment::OtherKey(k) => {
+                    pointer.push('/');
+                    pointer.push_str(&k.replace('~', "~0").replace('/', "~1"));
+                }
+                Segment::Index(i) => write!(pointer, "/{}", i)?,
+            }
+        }
+
+        if pointer.contains(char::is_control) || pointer.contains(" =>") {
+            f.write_str(&to_json_string(&pointer))
+        } else {
+            f.write_str(&pointer)
+        }
+    }
+
+    /// Unquoted pointers have no delimiters, so they are assumed to end at the ` => ` separator.
+    /// Tokens that are valid array indexes are parsed as indexes.
+    fn parse<'a>(&self, s: &'a str) -> ParseResult<'a, Path> {
+        let (pointer, rest) = if s.starts_with('"') {
+            parse_json_string(s)?
+        } else {
+            let end = s.find(" => ").unwrap_or(s.len());
+            (s[..end].to_string(), &s[end..])
+        };
+
+        if pointer.is_empty() {
+            return Ok((Path::default(), rest));
+        }
+
+        let tokens = pointer
+            .strip_prefix('/')
+            .ok_or("JSON Pointers must start with '/'")?;
+
+        let mut path = Path::default();
+        for token in tokens.split('/') {
+            let is_index = token == "0"
+                || (!token.starts_with('0') && token.chars().all(|c| c.is_ascii_digit()));
+
+            match token.parse() {
+                Ok(index) if is_index => path.push(Segment::Index(index)),
+                _ => path.push(Segment::Key(Self::parse_token(token))),
+            }
+        }
+
+        Ok((path, rest))
+    }
+}
+
+impl JsonPointerDialect {
+    /// Decodes the `~0` and `~1` escape sequences of a reference token. Any other `~` is kept.
+    fn parse_token(token: &str) -> String {
+        let mut key = String::new();
+        let mut chars = token.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '~' => match chars.next_if(|c| matches!(c, '0' | '1')) {
+                    Some('1') => key.push('/'),
+                    _ => key.push('~'),
+                },
+                c => key.push(c),
+            }
+        }
+
+        key
+    }
+}
+
+/// Bracket notation with JSON encoded keys, e.g. `["foo"]["first name"][0]`.
+pub(super) struct BracketDialect;
+
+impl PathDialect for BracketDialect {
+    fn fmt(&self, path: &Path, f: &mut Formatter<'_>) -> fmt::Result {
+        for segment in path.iter() {
+            match segment {
+                Segment::Key(k) | Segment::OtherKey(k) => write!(f, "[{}]", to_json_string(k))?,
+                Segment::Index(i) => write!(f, "[{}]", i)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse<'a>(&self, s: &'a str) -> ParseResult<'a, Path> {
+        let mut path = Path::default();
+        let mut rest = s;
+
+        while let Some(r) = rest.strip_prefix('[') {
+            let (segment, r) = if r.starts_with('"') {
+                let (key, r) = parse_json_string(r)?;
+                (Segment::Key(key), r)
+            } else {
+                let (index, r) = parse_index(r)?;
+                (Segment::Index(index), r)
+            };
+            path.push(segment);
+            rest = r.strip_prefix(']').ok_or("expected ']'")?;
+        }
+
+        Ok((path, rest))
+    }
+}
+
+/// Words that `gron` never uses as a bare identifier.
+const GRON_RESERVED_WORDS: &[&str] = &[
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "new",
+    "null",
+    "return",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+/// The paths printed by `gron`, e.g. `json.foo["first name"][0]`.
+pub(super) struct GronDialect(pub(super) Identifiers);
+
+impl GronDialect {
+    /// Whether the key is a JavaScript identifier, which may also contain dollar signs.
+    fn is_identifier(&self, key: &str) -> bool {
+        let mut chars = key.chars();
+
+        matches!(chars.next(), Some(c) if self.0.is_start(c) || c == '$')
+            && chars.all(|c| self.0.is_continue(c) || c == '$')
+            && !GRON_RESERVED_WORDS.contains(&key)
+    }
+}
+
+impl PathDialect for GronDialect {
+    fn fmt(&self, path: &Path, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("json")?;
+
+        for segment in path.iter() {
+            match segment {
+                Segment::Key(k) | Segment::OtherKey(k) if self.is_identifier(k) => {
+                    write!(f, ".{}", k)?
+                }
+                Segment::Key(k) | Segment::OtherKey(k) => write!(f, "[{}]", to_json_string(k))?,
+                Segment::Index(i) => write!(f, "[{}]", i)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse<'a>(&self, s: &'a str) -> ParseResult<'a, Path> {
+        let mut path = Path::default();
+        let mut rest = s
+            .strip_prefix("json")
+            .ok_or("gron paths must start with 'json'")?;
+
+        loop {
+            if let Some(r) = rest.strip_prefix('.') {
+                let (key, r) = parse_identifier(r)?;
+                if key.is_empty() {
+                    return Err("expected a key after '.'".to_string());
+                }
+                path.push(Segment::Key(key));
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix('[') {
+                let (segment, r) = if r.starts_with('"') {
+                    let (key, r) = parse_json_string(r)?;
+                    (Segment::Key(key), r)
+                } else {
+                    let (index, r) = parse_index(r)?;
+                    (Segment::Index(index), r)
+                };
+                path.push(segment);
+                rest = r.strip_prefix(']').ok_or("expected ']'")?;
+            } else {
+                return Ok((path, rest));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_escape_path_element {
+    use super::{escape_path_element, Identifiers};
+
+    #[test]
+    fn test_nothing_to_escape() {
+        assert_eq!(escape_path_element("foo"), "foo");
+        assert_eq!(
+            escape_path_element("key_with_underscores"),
+            "key_with_underscores"
+        );
+    }
+
+    #[test]
+    fn test_wrap_strings_with_non_alnum_chars_in_double_quotes() {
+        assert_eq!(
+            escape_path_element("Key with brackets [0]"),
+            "\"Key with brackets [0]\""
+        );
+        assert_eq!(
+            escape_path_element("key-with-dashes"),
+            "\"key-with-dashes\""
+        );
+    }
+
+    #[test]
+    /// Keys composed of only digits need to be wrapped in quotes
+    fn test_wrap_strings_with_only_digits_in_double_quotes() {
+        assert_eq!(escape_path_element("123"), "\"123\"");
+        assert_eq!(escape_path_element("abc123"), "abc123");
+    }
+
+    #[test]
+    /// Keys that would read as a key that isn't a string need to be wrapped in quotes
+    fn test_wrap_keywords_in_double_quotes() {
+        assert_eq!(escape_path_element("true"), "\"true\"");
+        assert_eq!(escape_path_element("null"), "\"null\"");
+        assert_eq!(escape_path_element("nullable"), "nullable");
+    }
+
+    #[test]
+    fn test_escape_double_quotes() {
+        assert_eq!(
+            escape_path_element("Mathieu \"Uncle Matt\" Lemay"),
+            r#""Mathieu \"Uncle Matt\" Lemay""#
+        );
+    }
+
+    #[test]
+    fn test_ascii_identifiers() {
+        assert_eq!(escape_path_element("abc_123"), "abc_123");
+        assert_eq!(escape_path_element("libellé"), "\"libellé\"");
+        assert_eq!(escape_path_element("名前"), "\"名前\"");
+    }
+
+    #[test]
+    fn test_unicode_identifiers() {
+        assert!(Identifiers::Unicode.is_identifier("libellé"));
+        assert!(Identifiers::Unicode.is_identifier("名前"));
+        assert!(!Identifiers::Unicode.is_identifier("1abc"));
+        assert!(Identifiers::Unicode.is_identifier("a·b"));
+        assert!(!Identifiers::Unicode.is_identifier("a€"));
+    }
+
+    #[test]
+    fn test_escape_backslashes_and_control_characters() {
+        assert_eq!(escape_path_element(r"a\b"), r#""a\\b""#);
+        assert_eq!(
+            escape_path_element("a\tb\u{1b}[0m\u{85}"),
+            r#""a\tb\u001b[0m\u0085""#
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_path_dialects {
+    use super::{
+        BracketDialect, GronDialect, Identifiers, JqDialect, JsonPathDialect, JsonPointerDialect,
+        Path, PathDialect, Segment,
+    };
+
+    fn path(document: Option<Segment>, segments: &[Segment]) -> Path {
+        let mut path = Path::new(document);
+        for segment in segments {
+            path.push(segment.clone());
+        }
+        path
+    }
+
+    fn segments() -> Vec<Segment> {
+        vec![
+            Segment::Key("foo".to_string()),
+            Segment::Key("first name".to_string()),
+            Segment::Index(0),
+            Segment::Key("a/b~c 'd' \"e\" \\".to_string()),
+        ]
+    }
+
+    /// Checks that the path is printed as expected, and that it is parsed back to the same path.
+    fn assert_round_trip(dialect: &dyn PathDialect, path: Path, expected: &str) {
+        assert_eq!(path.display(dialect).to_string(), expected);
+        assert_eq!(
+            dialect.parse(&format!("{} => 1", expected)),
+            Ok((path, " => 1"))
+        );
+    }
+
+    #[test]
+    fn test_jq() {
+        assert_round_trip(&JqDialect, Path::default(), ".");
+        assert_round_trip(&JqDialect, path(None, &[Segment::Index(0)]), ".[0]");
+        assert_round_trip(
+            &JqDialect,
+            path(None, &segments()),
+            r#".foo."first name"[0]."a/b~c 'd' \"e\" \\""#,
+        );
+        assert_round_trip(
+            &JqDialect,
+            path(None, &[Segment::Key("a\tb\u{1b}".to_string())]),
+            r#"."a\tb\u001b""#,
+        );
+        assert_round_trip(
+            &JqDialect,
+            path(Some(Segment::Index(3)), &segments()[..1]),
+            "[3].foo",
+        );
+        assert_round_trip(
+            &JqDialect,
+            path(Some(Segment::Key("a \"b\"".to_string())), &[]),
+            r#"["a \"b\""]"#,
+        );
+        assert_round_trip(
+            &JqDialect,
+            path(None, &[Segment::Key("".to_string())]),
+            r#"."""#,
+        );
+    }
+
+    #[test]
+    fn test_jq_other_keys() {
+        let other = |k: &str| Segment::OtherKey(k.to_string());
+
+        assert_round_trip(&JqDialect, path(None, &[other("200")]), ".200");
+        assert_round_trip(
+            &JqDialect,
+            path(None, &[Segment::Key("200".to_string()), other("-1")]),
+            r#"."200".-1"#,
+        );
+        assert_round_trip(
+            &JqDialect,
+            path(None, &[Segment::Key("true".to_string()), other("null")]),
+            r#"."true".null"#,
+        );
+        assert_round_trip(
+            &JqDialect,
+            path(None, &[other("1.5"), other("1e100"), Segment::Index(0)]),
+            ".[1.5].1e100[0]",
+        );
+        assert_round_trip(
+            &JqDialect,
+            path(
+                None,
+                &[Segment::Key("foo".to_string()), other(r#"{a: [1, "]"]}"#)],
+            ),
+            r#".foo[{a: [1, "]"]}]"#,
+        );
+        assert_round_trip(
+            &JqDialect,
+            path(Some(Segment::Index(1)), &[other("[a, b]")]),
+            "[1][[a, b]]",
+        );
+    }
+
+    #[test]
+    fn test_jq_invalid_paths() {
+        assert!(JqDialect.parse("foo => 1").is_err());
+        assert!(JqDialect.parse(".foo[1 => 1").is_err());
+        assert!(JqDialect.parse(".foo[a] => 1").is_err());
+        assert!(JqDialect.parse(".foo[] => 1").is_err());
+        assert!(JqDialect.parse(".foo[[a] => 1").is_err());
+        assert!(JqDialect.parse(r#"."foo => 1"#).is_err());
+    }
+
+    #[test]
+    fn test_jsonpath() {
+        assert_round_trip(&JsonPathDialect(Identifiers::Unicode), Path::default(), "$");
+        assert_round_trip(
+            &JsonPathDialect(Identifiers::Unicode),
+            path(None, &segments()),
+            r#"$.foo['first name'][0]['a/b~c \'d\' "e" \\']"#,
+        );
+        assert_round_trip(
+            &JsonPathDialect(Identifiers::Unicode),
+            path(None, &[Segment::Index(3), Segment::Key("foo".to_string())]),
+            "$[3].foo",
+        );
+        assert_round_trip(
+            &JsonPathDialect(Identifiers::Unicode),
+            path(None, &[Segment::Key("123".to_string())]),
+            "$['123']",
+        );
+        assert_round_trip(
+            &JsonPathDialect(Identifiers::Unicode),
+            path(None, &[Segment::Key("a\nb".to_string())]),
+            r#"$['a\u000ab']"#,
+        );
+    }
+
+    #[test]
+    fn test_unicode_and_ascii_identifiers() {
+        let keys = path(
+            None,
+            &[
+                Segment::Key("名前".to_string()),
+                Segment::Key("libellé".to_string()),
+            ],
+        );
+
+        assert_round_trip(&JqDialect, keys.clone(), r#"."名前"."libellé""#);
+        assert_round_trip(
+            &JsonPathDialect(Identifiers::Unicode),
+            keys.clone(),
+            "$.名前.libellé",
+        );
+        assert_round_trip(
+            &JsonPathDialect(Identifiers::Ascii),
+            keys.clone(),
+            "$['名前']['libellé']",
+        );
+        assert_round_trip(
+            &GronDialect(Identifiers::Unicode),
+            keys.clone(),
+            "json.名前.libellé",
+        );
+        assert_round_trip(
+            &GronDialect(Identifiers::Ascii),
+            keys,
+            r#"json["名前"]["libellé"]"#,
+        );
+    }
+
+    #[test]
+    fn test_jsonpath_invalid_paths() {
+        assert!(JsonPathDialect(Identifiers::Unicode)
+            .parse(".foo => 1")
+            .is_err());
+        assert!(JsonPathDialect(Identifiers::Unicode)
+            .parse("$. => 1")
+            .is_err());
+        assert!(JsonPathDialect(Identifiers::Unicode)
+            .parse("$['foo] => 1")
+            .is_err());
+        assert!(JsonPathDialect(Identifiers::Unicode)
+            .parse(r#"$['\x'] => 1"#)
+            .is_err());
+    }
+
+    #[test]
+    fn test_pointer() {
+        assert_round_trip(&JsonPointerDialect, Path::default(), "");
+        assert_round_trip(
+            &JsonPointerDialect,
+            path(None, &segments()),
+            r#"/foo/first name/0/a~1b~0c 'd' "e" \"#,
+        );
+        assert_round_trip(
+            &JsonPointerDialect,
+            path(None, &[Segment::Key("\u{1b}[31m\n\u{7f}".to_string())]),
+            r#""/\u001b[31m\n\u007f""#,
+        );
+        assert_round_trip(
+            &JsonPointerDialect,
+            path(
+                None,
+                &[
+                    Segment::Key("a => b".to_string()),
+                    Segment::Key("c =>".to_string()),
+                ],
+            ),
+            r#""/a => b/c =>""#,
+        );
+        assert_round_trip(
+            &JsonPointerDialect,
+            path(None, &[Segment::Key("a =".to_string())]),
+            "/a =",
+        );
+        assert_eq!(
+            JsonPointerDialect.parse("/~2/a~01"),
+            Ok((
+                path(
+                    None,
+                    &[
+                        Segment::Key("~2".to_string()),
+                        Segment::Key("a~1".to_string())
+                    ]
+                ),
+                ""
+            ))
+        );
+        assert_round_trip(
+            &JsonPointerDialect,
+            path(None, &[Segment::Index(3), Segment::Key("foo".to_string())]),
+            "/3/foo",
+        );
+        assert_round_trip(
+            &JsonPointerDialect,
+            path(None, &[Segment::Key("01".to_string())]),
+            "/01",
+        );
+        assert!(JsonPointerDialect.parse("foo => 1").is_err());
+        assert!(JsonPointerDialect.parse(r#""/\x" => 1"#).is_err());
+        assert!(JsonPointerDialect.parse(r#""/a => 1"#).is_err());
+    }
+
+    #[test]
+    fn test_bracket() {
+        assert_round_trip(&BracketDialect, Path::default(), "");
+        assert_round_trip(
+            &BracketDialect,
+            path(None, &segments()),
+            r#"["foo"]["first name"][0]["a/b~c 'd' \"e\" \\"]"#,
+        );
+        assert_round_trip(
+            &BracketDialect,
+            path(None, &[Segment::Index(3), Segment::Key("foo".to_string())]),
+            r#"[3]["foo"]"#,
+        );
+        assert!(BracketDialect.parse(r#"["foo] => 1"#).is_err());
+    }
+
+    #[test]
+    fn test_gron() {
+        assert_round_trip(&GronDialect(Identifiers::Unicode), Path::default(), "json");
+        assert_round_trip(
+            &GronDialect(Identifiers::Unicode),
+            path(None, &segments()),
+            r#"json.foo["first name"][0]["a/b~c 'd' \"e\" \\"]"#,
+        );
+        assert_round_trip(
+            &GronDialect(Identifiers::Unicode),
+            path(
+                None,
+                &[
+                    Segment::Key("class".to_string()),
+                    Segment::Key("libellé".to_string()),
+                    Segment::Key("123".to_string()),
+                ],
+            ),
+            r#"json["class"].libellé["123"]"#,
+        );
+    }
+
+    #[test]
+    fn test_gron_invalid_paths() {
+        assert!(GronDialect(Identifiers::Unicode)
+            .parse(".foo = 1;")
+            .is_err());
+        assert!(GronDialect(Identifiers::Unicode)
+            .parse("json. = 1;")
+            .is_err());
+        assert!(GronDialect(Identifiers::Unicode)
+            .parse("json[1 = 1;")
+            .is_err());
+        assert!(GronDialect(Identifiers::Unicode)
+            .parse(r#"json["foo] = 1;"#)
+            .is_err());
+    }
+}
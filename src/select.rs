@@ -0,0 +1,377 @@
+use crate::error::{Error, ErrorKind, Result};
+use crate::path::{parse_identifier, parse_index, parse_json_string, ParseResult, Segment};
+
+/// A step of a path glob.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Key(String),
+    Index(usize),
+    /// `*` or `[*]`, any key or index.
+    Any,
+    /// `..`, any number of keys and indices, including none.
+    Descent,
+}
+
+/// Parses a jq-like path with wildcards, e.g. `.spec.containers[*].image` or `..password`.
+fn parse_glob(s: &str) -> std::result::Result<Vec<Step>, String> {
+    let mut steps = Vec::new();
+    let mut rest = s;
+
+    if !rest.starts_with('.') {
+        return Err("paths must start with '.'".to_string());
+    }
+
+    while !rest.is_empty() {
+        let (step, r) = if let Some(r) = rest.strip_prefix("..") {
+            (Step::Descent, r)
+        } else if let Some(r) = rest.strip_prefix('.') {
+            match r.chars().next() {
+                // A dot is only followed by a bracket or nothing at the root, e.g. `.[0]`.
+                None | Some('[') if steps.is_empty() => {
+                    rest = r;
+                    continue;
+                }
+                _ => parse_key(r)?,
+            }
+        } else if let Some(r) = rest.strip_prefix('[') {
+            let (step, r) = if let Some(r) = r.strip_prefix('*') {
+                (Step::Any, r)
+            } else if r.starts_with('"') {
+                let (key, r) = parse_json_string(r)?;
+                (Step::Key(key), r)
+            } else {
+                let (index, r) = parse_index(r)?;
+                (Step::Index(index), r)
+            };
+            (step, r.strip_prefix(']').ok_or("expected ']'")?)
+        } else {
+            return Err(format!("unexpected {:?}", rest));
+        };
+
+        // Keys follow the descent without a dot, e.g. `..password`.
+        if step == Step::Descent && !r.is_empty() && !r.starts_with(['.', '[']) {
+            let (key, r) = parse_key(r)?;
+            steps.extend([step, key]);
+            rest = r;
+        } else {
+            steps.push(step);
+            rest = r;
+        }
+    }
+
+    Ok(steps)
+}
+
+/// Parses a key after a dot, which may be a wildcard or a quoted key.
+fn parse_key(s: &str) -> ParseResult<'_, Step> {
+    if let Some(r) = s.strip_prefix('*') {
+        Ok((Step::Any, r))
+    } else if s.starts_with('"') {
+        let (key, r) = parse_json_string(s)?;
+        Ok((Step::Key(key), r))
+    } else {
+        match parse_identifier(s)? {
+            (key, _) if key.is_empty() => Err(format!("expected a key, found {:?}", s)),
+            (key, r) => Ok((Step::Key(key), r)),
+        }
+    }
+}
+
+/// How a path relates to the selected subtrees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Selection {
+    /// The path is in a selected subtree.
+    Selected,
+    /// The path isn't selected, but some of its descendants may be.
+    Partial,
+    /// Neither the path nor its descendants are selected.
+    Unselected,
+}
+
+/// Selects the subtrees of a document with path globs, and excludes others. All of the document
+/// is selected when there are no globs to select.
+#[derive(Debug, Default)]
+pub(super) struct Selector {
+    globs: Vec<Vec<Step>>,
+    exclude_globs: Vec<Vec<Step>>,
+}
+
+impl Selector {
+    pub(super) fn new(globs: &[String], exclude_globs: &[String]) -> Result<Self> {
+        let parse = |globs: &[String]| {
+            globs
+                .iter()
+                .map(|g| {
+                    parse_glob(g).map_err(|e| {
+                        Error::new(ErrorKind::Pattern, format!("invalid path {:?}: {}", g, e))
+                    })
+                })
+                .collect::<Result<_>>()
+        };
+
+        Ok(Self {
+            globs: parse(globs)?,
+            exclude_globs: parse(exclude_globs)?,
+        })
+    }
+
+    /// Whether the path is in an excluded subtree, which is left out even if it is selected.
+    pub(super) fn is_excluded(&self, path: &[Segment]) -> bool {
+        self.exclude_globs
+            .iter()
+            .any(|g| select(g, path) == Selection::Selected)
+    }
+
+    pub(super) fn select(&self, path: &[Segment]) -> Selection {
+        if self.globs.is_empty() {
+            return Selection::Selected;
+        }
+
+        let mut selection = Selection::Unselected;
+        for glob in &self.globs {
+            match select(glob, path) {
+                Selection::Selected => return Selection::Selected,
+                Selection::Partial => selection = Selection::Partial,
+                Selection::Unselected => {}
+            }
+        }
+
+        selection
+    }
+}
+
+/// Matches the path against a glob, keeping track of every step of the glob that the path can
+/// have reached, since descents match any number of segments.
+fn select(glob: &[Step], path: &[Segment]) -> Selection {
+    // Descents can also match no segment, in which case the next step is reached too.
+    let close = |positions: &mut Vec<bool>| {
+        for i in 0..glob.len() {
+            if positions[i] && glob[i] == Step::Descent {
+                positions[i + 1] = true;
+            }
+        }
+    };
+
+    let mut positions = vec![false; glob.len() + 1];
+    positions[0] = true;
+    close(&mut positions);
+
+    for segment in path {
+        if positions[glob.len()] {
+            return Selection::Selected;
+        }
+
+        let mut next = vec![false; glob.len() + 1];
+        for (i, step) in glob.iter().enumerate().filter(|(i, _)| positions[*i]) {
+            match (step, segment) {
+                (Step::Descent, _) => next[i] = true,
+                (Step::Any, _) => next[i + 1] = true,
+                (Step::Key(k), Segment::Key(s) | Segment::OtherKey(s)) if k == s => {
+                    next[i + 1] = true
+                }
+                (Step::Index(n), Segment::Index(index)) if n == index => next[i + 1] = true,
+                _ => {}
+            }
+        }
+        close(&mut next);
+
+        if !next.contains(&true) {
+            return Selection::Unselected;
+        }
+        positions = next;
+    }
+
+    if positions[glob.len()] {
+        Selection::Selected
+    } else {
+        Selection::Partial
+    }
+}
+
+#[cfg(test)]
+mod test_selector {
+    use super::{parse_glob, Selection, Selector, Step};
+    use crate::path::Segment;
+
+    fn key(k: &str) -> Segment {
+        Segment::Key(k.to_string())
+    }
+
+    fn strings(globs: &[&str]) -> Vec<String> {
+        globs.iter().map(|g| g.to_string()).collect()
+    }
+
+    fn selector(globs: &[&str]) -> Selector {
+        Selector::new(&strings(globs), &[]).unwrap()
+    }
+
+    #[test]
+    fn test_parse_glob() {
+        let k = |k: &str| Step::Key(k.to_string());
+
+        assert_eq!(parse_glob("."), Ok(vec![]));
+        assert_eq!(
+            parse_glob(".spec.containers[*].image"),
+            Ok(vec![k("spec"), k("containers"), Step::Any, k("image")])
+        );
+        assert_eq!(
+            parse_glob(r#".servers.*."ip address"[0]"#),
+            Ok(vec![
+                k("servers"),
+                Step::Any,
+                k("ip address"),
+                Step::Index(0)
+            ])
+        );
+        assert_eq!(
+            parse_glob("..password"),
+            Ok(vec![Step::Descent, k("password")])
+        );
+        assert_eq!(
+            parse_glob(r#".a..["b"]"#),
+            Ok(vec![k("a"), Step::Descent, k("b")])
+        );
+        assert_eq!(parse_glob(".[1]"), Ok(vec![Step::Index(1)]));
+        assert_eq!(parse_glob(".a.."), Ok(vec![k("a"), Step::Descent]));
+    }
+
+    #[test]
+    fn test_parse_invalid_globs() {
+        assert!(parse_glob("").is_err());
+        assert!(parse_glob("spec").is_err());
+        assert!(parse_glob(".a[").is_err());
+        assert!(parse_glob(".a[x]").is_err());
+        assert!(parse_glob(".a.").is_err());
+        assert!(Selector::new(&strings(&["spec"]), &[]).is_err());
+        assert!(Selector::new(&[], &strings(&[".a["])).is_err());
+    }
+
+    #[test]
+    fn test_no_globs() {
+        assert_eq!(Selector::default().select(&[]), Selection::Selected);
+    }
+
+    #[test]
+    fn test_select() {
+        let s = selector(&[".spec.containers[*].image"]);
+
+        assert_eq!(s.select(&[]), Selection::Partial);
+        assert_eq!(s.select(&[key("spec")]), Selection::Partial);
+        assert_eq!(s.select(&[key("metadata")]), Selection::Unselected);
+        assert_eq!(
+            s.select(&[key("spec"), key("containers"), Segment::Index(1)]),
+            Selection::Partial
+        );
+        assert_eq!(
+            s.select(&[
+                key("spec"),
+                key("containers"),
+                Segment::Index(1),
+                key("image")
+            ]),
+            Selection::Selected
+        );
+        assert_eq!(
+            s.select(&[
+                key("spec"),
+                key("containers"),
+                Segment::Index(1),
+                key("image"),
+                key("tag")
+            ]),
+            Selection::Selected
+        );
+        assert_eq!(
+            s.select(&[
+                key("spec"),
+                key("containers"),
+                Segment::Index(1),
+                key("name")
+            ]),
+            Selection::Unselected
+        );
+    }
+
+    #[test]
+    fn test_select_index() {
+        let s = selector(&[".a[12]", ".[1][0]"]);
+
+        assert_eq!(s.select(&[key("a")]), Selection::Partial);
+        assert_eq!(
+            s.select(&[key("a"), Segment::Index(12)]),
+            Selection::Selected
+        );
+        assert_eq!(
+            s.select(&[key("a"), Segment::Index(13)]),
+            Selection::Unselected
+        );
+        assert_eq!(
+            s.select(&[Segment::Index(1), Segment::Index(0)]),
+            Selection::Selected
+        );
+        assert_eq!(
+            s.select(&[Segment::Index(1), Segment::Index(1)]),
+            Selection::Unselected
+        );
+    }
+
+    #[test]
+    fn test_select_other_keys() {
+        let s = selector(&[".responses.200"]);
+
+        assert_eq!(
+            s.select(&[key("responses"), Segment::OtherKey("200".to_string())]),
+            Selection::Selected
+        );
+    }
+
+    #[test]
+    fn test_select_descent() {
+        let s = selector(&["..password"]);
+
+        assert_eq!(s.select(&[key("a")]), Selection::Partial);
+        assert_eq!(s.select(&[key("password")]), Selection::Selected);
+        assert_eq!(
+            s.select(&[key("a"), Segment::Index(0), key("password")]),
+            Selection::Selected
+        );
+
+        let s = selector(&[".a..c"]);
+        assert_eq!(s.select(&[key("b")]), Selection::Unselected);
+        assert_eq!(s.select(&[key("a"), key("c")]), Selection::Selected);
+        assert_eq!(
+            s.select(&[key("a"), key("b"), key("c")]),
+            Selection::Selected
+        );
+    }
+
+    #[test]
+    fn test_select_any_of_several_globs() {
+        let s = selector(&[".a.b", ".c"]);
+
+        assert_eq!(s.select(&[key("a")]), Selection::Partial);
+        assert_eq!(s.select(&[key("c"), key("d")]), Selection::Selected);
+        assert_eq!(s.select(&[key("d")]), Selection::Unselected);
+    }
+
+    #[test]
+    fn test_exclude() {
+        let s = Selector::new(&[], &strings(&[".metadata.managedFields", ".status"])).unwrap();
+
+        assert!(!s.is_excluded(&[]));
+        assert!(!s.is_excluded(&[key("metadata")]));
+        assert!(s.is_excluded(&[key("metadata"), key("managedFields")]));
+        assert!(s.is_excluded(&[key("status"), key("phase")]));
+        assert_eq!(s.select(&[key("status")]), Selection::Selected);
+    }
+
+    #[test]
+    fn test_exclude_index() {
+        let s = Selector::new(&[], &strings(&[".a[12]", ".[1][0]"])).unwrap();
+
+        assert!(s.is_excluded(&[key("a"), Segment::Index(12)]));
+        assert!(!s.is_excluded(&[key("a"), Segment::Index(13)]));
+        assert!(s.is_excluded(&[Segment::Index(1), Segment::Index(0)]));
+        assert!(!s.is_excluded(&[Segment::Index(1), Segment::Index(1)]));
+    }
+}
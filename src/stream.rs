@@ -0,0 +1,679 @@
+use std::io::BufRead;
+use std::str::FromStr;
+
+use crate::document::{Key, Mapping, Node, Number};
+use crate::error::{Error, ErrorKind, Result};
+use crate::path::{Path, Segment};
+use crate::value_writer::ValueWriter;
+
+/// Flattens JSON values as they are read, without building them in memory. Only the path of the
+/// current value and the current token are kept, so memory use is bounded by the depth of the
+/// document and the size of its largest scalar.
+///
+/// With `json_lines`, the input is a sequence of values, each printed under the index of the
+/// record. Otherwise the input must be a single value.
+pub(super) fn stream_json(
+    rd: impl BufRead,
+    json_lines: bool,
+    writer: &mut dyn ValueWriter,
+) -> Result<()> {
+    let mut parser = Parser::new(rd);
+
+    if !json_lines {
+        parser.write_value(&mut Path::default(), writer)?;
+        // The rest of the input isn't read once the writer is done.
+        if !writer.is_done() && parser.skip_whitespace()?.is_some() {
+            parser.next()?;
+            return Err(parser.error("trailing characters"));
+        }
+        return writer.flush();
+    }
+
+    let mut index = 0;
+    while !writer.is_done() && parser.start_record(index + 1)? {
+        parser.write_value(&mut Path::new(Some(Segment::Index(index))), writer)?;
+        if !writer.is_done() {
+            parser.end_record()?;
+        }
+        writer.flush()?;
+        index += 1;
+    }
+
+    Ok(())
+}
+
+/// Loads a JSON document. Unlike serde_json, the parser keeps the text of numbers as it is written
+/// in the input, and isn't limited in depth.
+pub(super) fn parse_json(rd: impl BufRead) -> Result<Node> {
+    let mut parser = Parser::new(rd);
+
+    let value = parser.parse_value()?;
+    if parser.skip_whitespace()?.is_some() {
+        parser.next()?;
+        return Err(parser.error("trailing characters"));
+    }
+
+    Ok(value)
+}
+
+/// Loads the records of a JSON Lines input one at a time. Each non-blank line must hold exactly
+/// one value.
+pub(super) fn parse_json_lines(rd: impl BufRead) -> impl Iterator<Item = Result<Node>> {
+    let mut parser = Parser::new(rd);
+    let mut number = 0;
+
+    std::iter::from_fn(move || {
+        number += 1;
+        match parser.start_record(number) {
+            Ok(true) => Some(parser.parse_value().and_then(|value| {
+                parser.end_record()?;
+                Ok(value)
+            })),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    })
+}
+
+impl FromStr for Node {
+    type Err = Error;
+
+    /// Parses a JSON document.
+    fn from_str(s: &str) -> Result<Self> {
+        parse_json(s.as_bytes())
+    }
+}
+
+/// A container being parsed.
+enum Container {
+    Object,
+    Array(usize),
+}
+
+/// A container being loaded, with the key of its next value for objects.
+enum PartialContainer {
+    Object(Mapping, String),
+    Array(Vec<Node>),
+}
+
+struct Parser<R> {
+    rd: R,
+    line: usize,
+    column: usize,
+    /// Bytes of the current string or number.
+    token: Vec<u8>,
+    /// Number of the JSON Lines record being parsed, which must not span several lines.
+    record: Option<usize>,
+}
+
+impl<R: BufRead> Parser<R> {
+    fn new(rd: R) -> Self {
+        Self {
+            rd,
+            line: 1,
+            column: 0,
+            token: Vec::new(),
+            record: None,
+        }
+    }
+
+    /// Builds a syntax error at the position of the last byte read.
+    fn error(&self, message: &str) -> Error {
+        let message = format!("{} at line {} column {}", message, self.line, self.column);
+
+        match self.record {
+            Some(number) => Error::new(
+                ErrorKind::JsonDeserialize,
+                format!("record {}: {}", number, message),
+            ),
+            None => Error::new(ErrorKind::JsonDeserialize, message),
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>> {
+        Ok(self.rd.fill_buf()?.first().copied())
+    }
+
+    fn next(&mut self) -> Result<Option<u8>> {
+        let b = self.peek()?;
+
+        if let Some(b) = b {
+            self.rd.consume(1);
+            if b == b'\n' {
+                self.line += 1;
+                self.column = 0;
+            } else {
+                self.column += 1;
+            }
+        }
+
+        Ok(b)
+    }
+
+    fn next_or_eof(&mut self) -> Result<u8> {
+        self.next()?
+            .ok_or_else(|| self.error("unexpected end of input"))
+    }
+
+    /// Skips whitespace and returns the next byte, which is left in the input. Within a record,
+    /// the end of the line is an error.
+    fn skip_whitespace(&mut self) -> Result<Option<u8>> {
+        while let Some(b) = self.peek()? {
+            if b == b'\n' && self.record.is_some() {
+                return Err(self.error("unexpected end of line"));
+            }
+            if !matches!(b, b' ' | b'\t' | b'\n' | b'\r') {
+                return Ok(Some(b));
+            }
+            self.next()?;
+        }
+
+        Ok(None)
+    }
+
+    /// Skips the blank lines before a JSON Lines record, and returns whether there is one.
+    fn start_record(&mut self, number: usize) -> Result<bool> {
+        self.record = None;
+        if self.skip_whitespace()?.is_none() {
+            return Ok(false);
+        }
+
+        self.record = Some(number);
+        Ok(true)
+    }
+
+    /// Checks that nothing but whitespace follows a record on its line.
+    fn end_record(&mut self) -> Result<()> {
+        while let Some(b) = self.peek()? {
+            match b {
+                b'\n' => break,
+                b' ' | b'\t' | b'\r' => {
+                    self.next()?;
+                }
+                _ => {
+                    self.next()?;
+                    return Err(self.error("trailing characters"));
+                }
+            }
+        }
+
+        self.record = None;
+        Ok(())
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<()> {
+        match self.skip_whitespace()? {
+            Some(b) if b == expected => {
+                self.next()?;
+                Ok(())
+            }
+            Some(_) => {
+                self.next()?;
+                Err(self.error(&format!("expected '{}'", expected as char)))
+            }
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn expect_literal(&mut self, literal: &str) -> Result<()> {
+        for expected in literal.bytes() {
+            if self.next_or_eof()? != expected {
+                return Err(self.error(&format!("expected '{}'", literal)));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses a string, whose opening quote is the next byte of the input.
+    fn parse_string(&mut self) -> Result<String> {
+        let quote = self.next_or_eof()?;
+        self.token.clear();
+        self.token.push(quote);
+
+        let mut escaped = false;
+        let mut has_escapes = false;
+        loop {
+            let b = self.next_or_eof()?;
+            self.token.push(b);
+
+            match b {
+                b'"' if !escaped => break,
+                b'\\' => {
+                    escaped = !escaped;
+                    has_escapes = true;
+                }
+                0x00..=0x1f => return Err(self.error("control character in string")),
+                _ => escaped = false,
+            }
+        }
+
+        let s = if has_escapes {
+            serde_json::from_slice(&self.token).map_err(|e| self.error(&e.to_string()))?
+        } else {
+            std::str::from_utf8(&self.token[1..self.token.len() - 1])?.to_string()
+        };
+
+        Ok(s)
+    }
+
+    fn parse_number(&mut self) -> Result<Number> {
+        self.token.clear();
+        while let Some(b) = self.peek()? {
+            if !matches!(b, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E') {
+                break;
+            }
+            self.token.push(b);
+            self.next()?;
+        }
+
+        std::str::from_utf8(&self.token)?
+            .parse()
+            .map_err(|_| self.error("invalid number"))
+    }
+
+    /// Parses an object key and the colon that follows it.
+    fn parse_key(&mut self) -> Result<String> {
+        if self.skip_whitespace()? != Some(b'"') {
+            self.next()?;
+            return Err(self.error("expected a key"));
+        }
+        let key = self.parse_string()?;
+        self.expect(b':')?;
+
+        Ok(key)
+    }
+
+    /// Loads the next value in the input. Like `write_value`, containers are kept on an explicit
+    /// stack.
+    fn parse_value(&mut self) -> Result<Node> {
+        let mut stack = Vec::new();
+
+        loop {
+            let mut value = match self.skip_whitespace()? {
+                Some(b'{') => {
+                    self.next()?;
+                    if self.skip_whitespace()? == Some(b'}') {
+                        self.next()?;
+                        Node::Object(Mapping::new())
+                    } else {
+                        let key = self.parse_key()?;
+                        stack.push(PartialContainer::Object(Mapping::new(), key));
+                        continue;
+                    }
+                }
+                Some(b'[') => {
+                    self.next()?;
+                    if self.skip_whitespace()? == Some(b']') {
+                        self.next()?;
+                        Node::Array(Vec::new())
+                    } else {
+                        stack.push(PartialContainer::Array(Vec::new()));
+                        continue;
+                    }
+                }
+                Some(b'"') => Node::String(self.parse_string()?),
+                Some(b't') => {
+                    self.expect_literal("true")?;
+                    Node::Bool(true)
+                }
+                Some(b'f') => {
+                    self.expect_literal("false")?;
+                    Node::Bool(false)
+                }
+                Some(b'n') => {
+                    self.expect_literal("null")?;
+                    Node::Null
+                }
+                Some(b'-' | b'0'..=b'9') => Node::Number(self.parse_number()?),
+                Some(_) => {
+                    self.next()?;
+                    return Err(self.error("expected a value"));
+                }
+                None => return Err(self.error("unexpected end of input")),
+            };
+
+            // Adds the value to its container, and the containers that end after it to theirs.
+            loop {
+                let container = match stack.last_mut() {
+                    Some(container) => container,
+                    None => return Ok(value),
+                };
+                let next = self.skip_whitespace()?;
+
+                // As with serde_json, a duplicate key keeps its first position and its last value.
+                match container {
+                    PartialContainer::Object(o, key) => {
+                        o.insert(Key::String(std::mem::take(key)), value);
+                    }
+                    PartialContainer::Array(a) => a.push(value),
+                }
+
+                match (container, next) {
+                    (PartialContainer::Object(_, key), Some(b',')) => {
+                        self.next()?;
+                        *key = self.parse_key()?;
+                        break;
+                    }
+                    (PartialContainer::Array(_), Some(b',')) => {
+                        self.next()?;
+                        break;
+                    }
+                    (PartialContainer::Object(..), Some(b'}'))
+                    | (PartialContainer::Array(_), Some(b']')) => {
+                        self.next()?;
+                        value = match stack.pop() {
+                            Some(PartialContainer::Object(o, _)) => Node::Object(o),
+                            Some(PartialContainer::Array(a)) => Node::Array(a),
+                            None => unreachable!("the container was just added to"),
+                        };
+                    }
+                    (container, _) => {
+                        self.next()?;
+                        let message = match container {
+                            PartialContainer::Object(..) => "expected ',' or '}'",
+                            PartialContainer::Array(_) => "expected ',' or ']'",
+                        };
+                        return Err(self.error(message));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Writes the scalars and empty containers of the next value in the input. Containers are
+    /// kept on an explicit stack, so that the depth of the document isn't limited by the size of
+    /// the call stack.
+    fn write_value(&mut self, path: &mut Path, writer: &mut dyn ValueWriter) -> Result<()> {
+        let mut stack = Vec::new();
+
+        loop {
+            match self.skip_whitespace()? {
+                Some(b'{') => {
+                    self.next()?;
+                    if self.skip_whitespace()? == Some(b'}') {
+                        self.next()?;
+                        writer.write_raw(path, "{}")?;
+                    } else {
+                        path.push(Segment::Key(self.parse_key()?));
+                        stack.push(Container::Object);
+                        continue;
+                    }
+                }
+                Some(b'[') => {
+                    self.next()?;
+                    if self.skip_whitespace()? == Some(b']') {
+                        self.next()?;
+                        writer.write_raw(path, "[]")?;
+                    } else {
+                        path.push(Segment::Index(0));
+                        stack.push(Container::Array(0));
+                        continue;
+                    }
+                }
+                Some(b'"') => {
+                    let s = self.parse_string()?;
+                    writer.write_string(path, &s)?;
+                }
+                Some(b't') => {
+                    self.expect_literal("true")?;
+                    writer.write_bool(path, true)?;
+                }
+                Some(b'f') => {
+                    self.expect_literal("false")?;
+                    writer.write_bool(path, false)?;
+                }
+                Some(b'n') => {
+                    self.expect_literal("null")?;
+                    writer.write_null(path)?;
+                }
+                Some(b'-' | b'0'..=b'9') => {
+                    let n = self.parse_number()?;
+                    writer.write_number(path, &n)?;
+                }
+                Some(_) => {
+                    self.next()?;
+                    return Err(self.error("expected a value"));
+                }
+                None => return Err(self.error("unexpected end of input")),
+            }
+
+            if writer.is_done() {
+                return Ok(());
+            }
+
+            // Moves to the next value, leaving the containers that have been fully parsed.
+            loop {
+                let container = match stack.last_mut() {
+                    Some(container) => container,
+                    None => return Ok(()),
+                };
+                let next = self.skip_whitespace()?;
+
+                match (container, next) {
+                    (Container::Object, Some(b',')) => {
+                        self.next()?;
+                        path.pop();
+                        path.push(Segment::Key(self.parse_key()?));
+                        break;
+                    }
+                    (Container::Array(i), Some(b',')) => {
+                        self.next()?;
+                        *i += 1;
+                        path.pop();
+                        path.push(Segment::Index(*i));
+                        break;
+                    }
+                    (Container::Object, Some(b'}')) | (Container::Array(_), Some(b']')) => {
+                        self.next()?;
+                        path.pop();
+                        stack.pop();
+                    }
+                    (container, _) => {
+                        self.next()?;
+                        let message = match container {
+                            Container::Object => "expected ',' or '}'",
+                            Container::Array(_) => "expected ',' or ']'",
+                        };
+                        return Err(self.error(message));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_stream_json {
+    use super::{parse_json_lines, stream_json};
+    use crate::document::Node;
+    use crate::path::{JqDialect, Path};
+    use crate::value_writer::WriterOptions;
+    use crate::value_writer::{get_writer, print_value, PrintOptions};
+    use crate::CmdColor;
+
+    fn stream(input: &str, json_lines: bool) -> String {
+        let mut buffer = Vec::new();
+        let mut writer = get_writer(
+            &mut buffer,
+            CmdColor::Never,
+            false,
+            &JqDialect,
+            None,
+            WriterOptions::default(),
+        );
+        stream_json(input.as_bytes(), json_lines, &mut *writer).unwrap();
+        drop(writer);
+
+        String::from_utf8(buffer).unwrap()
+    }
+
+    fn flatten(input: &str) -> String {
+        let mut buffer = Vec::new();
+        let mut writer = get_writer(
+            &mut buffer,
+            CmdColor::Never,
+            false,
+            &JqDialect,
+            None,
+            WriterOptions::default(),
+        );
+        let value: Node = input.parse().unwrap();
+        print_value(
+            &mut Path::default(),
+            value,
+            &mut *writer,
+            &PrintOptions::default(),
+        )
+        .unwrap();
+        drop(writer);
+
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn test_same_output_as_loaded_document() {
+        let input = r#"
+        {
+            "address": {"city": "London", "street": "10 Downing Street"},
+            "age": 43,
+            "empty": {"array": [], "object": {}},
+            "first name": "John \"Johnny\"\né😀",
+            "phones": ["+44 1234567", null, true, false, -1.5e3, [[0]]]
+        }"#;
+
+        assert_eq!(stream(input, false), flatten(input));
+    }
+
+    #[test]
+    fn test_scalar_document() {
+        assert_eq!(stream(" 42 ", false), ". => 42\n");
+        assert_eq!(stream("\"foo\"", false), ". => \"foo\"\n");
+        assert_eq!(stream("[]", false), ". => []\n");
+    }
+
+    #[test]
+    fn test_numbers_are_printed_as_written() {
+        let input = "[1.50, 1e3, -0, 123456789012345678901234567890]";
+
+        assert_eq!(
+            stream(input, false),
+            ".[0] => 1.50\n.[1] => 1e3\n.[2] => -0\n.[3] => 123456789012345678901234567890\n"
+        );
+    }
+
+    #[test]
+    fn test_keys_are_printed_in_input_order() {
+        assert_eq!(stream(r#"{"b": 1, "a": 2}"#, false), ".b => 1\n.a => 2\n");
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let input = "{\"name\": \"foo\"}\n[1]\n\n\"bar\"\n";
+
+        assert_eq!(
+            stream(input, true),
+            "[0].name => \"foo\"\n[1][0] => 1\n[2] => \"bar\"\n"
+        );
+    }
+
+    #[test]
+    fn test_deeply_nested_document() {
+        let depth = 100_000;
+        let input = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+
+        let expected = format!(".{} => []\n", "[0]".repeat(depth - 1));
+        assert_eq!(stream(&input, false), expected);
+    }
+
+    #[test]
+    fn test_invalid_documents_are_rejected() {
+        for input in [
+            "",
+            "{",
+            "[1,]",
+            "[1 2]",
+            r#"{"a" 1}"#,
+            r#"{"a": 1,}"#,
+            r#"{1: 1}"#,
+            "[tru]",
+            "[01]",
+            "[-]",
+            "\"a\nb\"",
+            r#""\x""#,
+            "1 2",
+        ] {
+            let mut buffer = Vec::new();
+            let mut writer = get_writer(
+                &mut buffer,
+                CmdColor::Never,
+                false,
+                &JqDialect,
+                None,
+                WriterOptions::default(),
+            );
+
+            assert!(
+                stream_json(input.as_bytes(), false, &mut *writer).is_err(),
+                "{:?} is not valid JSON",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_errors_have_a_position() {
+        let mut buffer = Vec::new();
+        let mut writer = get_writer(
+            &mut buffer,
+            CmdColor::Never,
+            false,
+            &JqDialect,
+            None,
+            WriterOptions::default(),
+        );
+
+        let err = stream_json("{\n  \"a\": x\n}".as_bytes(), false, &mut *writer).unwrap_err();
+        assert!(err.to_string().contains("line 2 column 8"), "{}", err);
+    }
+
+    #[test]
+    fn test_json_lines_records_are_single_lines() {
+        for (input, error) in [
+            (
+                "{\"a\": 1} {\"b\": 2}\n",
+                "record 1: trailing characters at line 1 column 10",
+            ),
+            (
+                "[1]\n{\"a\":\n1}\n",
+                "record 2: unexpected end of line at line 2 column 5",
+            ),
+            (
+                "[1]\n\n[2",
+                "record 2: expected ',' or ']' at line 3 column 2",
+            ),
+        ] {
+            let err = parse_json_lines(input.as_bytes())
+                .find_map(|r| r.err())
+                .unwrap();
+            assert!(err.to_string().ends_with(error), "{}", err);
+
+            let mut buffer = Vec::new();
+            let mut writer = get_writer(
+                &mut buffer,
+                CmdColor::Never,
+                false,
+                &JqDialect,
+                None,
+                WriterOptions::default(),
+            );
+            let err = stream_json(input.as_bytes(), true, &mut *writer).unwrap_err();
+            assert!(err.to_string().ends_with(error), "{}", err);
+        }
+
+        let records: Vec<Node> = parse_json_lines("  1 \r\n\n\t[2]\t\n".as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+    }
+}
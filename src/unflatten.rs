@@ -0,0 +1,949 @@
+use std::fmt::{self, Display, Formatter};
+use std::io::{BufRead, Write};
+
+use serde::ser::{Error as _, SerializeMap};
+use serde::{Serialize, Serializer};
+use toml::value::Datetime;
+
+use crate::document::{decode_binary, encode_binary, Key, Mapping, Node, Number};
+use crate::error::{Error, ErrorKind, Result};
+use crate::path::{GronDialect, Identifiers, Path, PathDialect, Segment};
+use crate::yaml;
+use crate::CmdOutputType;
+
+/// Number of missing items that an index can add to an array as nulls. json-struct prints every
+/// item, so indices far past the end of an array are mistakes that would exhaust memory.
+const MAX_INDEX_GAP: usize = 1 << 16;
+
+/// Rebuilds a document from the `path => value` lines printed by json-struct, or from the
+/// assignments printed by `gron`. Paths are read in the given dialect.
+pub(super) fn unflatten(rd: impl BufRead, dialect: &dyn PathDialect, gron: bool) -> Result<Node> {
+    let mut root = Node::Null;
+    // As when loading YAML, the number of nodes that aliases can still copy.
+    let mut expansion_budget: usize = 0;
+
+    for (i, line) in rd.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        expansion_budget =
+            expansion_budget.saturating_add(line.len().saturating_mul(yaml::ALIAS_EXPANSION_RATIO));
+
+        let parsed = if gron {
+            parse_gron_line(&line)
+        } else {
+            parse_line(&line, dialect)
+        };
+        parsed
+            .and_then(|(path, value)| {
+                let value = match value {
+                    LineValue::Value(value) => value,
+                    LineValue::Alias(anchor) => copy_anchor(&root, &anchor, &mut expansion_budget)?,
+                };
+                insert_value(&mut root, &path, value)
+            })
+            .map_err(|e| Error::new(ErrorKind::Unflatten, format!("line {}: {}", i + 1, e)))?;
+    }
+
+    Ok(root)
+}
+
+/// The value of a line, or the path of the value that it is an alias of.
+enum LineValue {
+    Value(Node),
+    Alias(Path),
+}
+
+/// Copies the value of an anchor for an alias. YAML anchors are printed before their aliases,
+/// so the value has already been rebuilt.
+fn copy_anchor(
+    root: &Node,
+    anchor: &Path,
+    expansion_budget: &mut usize,
+) -> std::result::Result<Node, String> {
+    let mut current = root;
+
+    for segment in anchor.iter() {
+        while let Node::Tagged(_, value) = current {
+            current = value;
+        }
+        let value = match (segment, current) {
+            (Segment::Key(k), Node::Object(o)) => o.get(&Key::String(k.clone())),
+            (Segment::OtherKey(k), Node::Object(o)) => o.get(&Key::Other(k.clone())),
+            (Segment::Index(i), Node::Array(a)) => a.get(*i),
+            _ => None,
+        };
+        current = value.ok_or("alias of a value that isn't set")?;
+    }
+
+    *expansion_budget = expansion_budget
+        .checked_sub(yaml::count_nodes(current))
+        .ok_or("repetition limit exceeded")?;
+
+    Ok(current.clone())
+}
+
+pub(super) fn write_document(
+    writer: &mut dyn Write,
+    node: &Node,
+    output_type: CmdOutputType,
+) -> Result<()> {
+    // The document is written once it is serialized, so that no part of it is written when a
+    // value can't be.
+    let document = Document::new(node, output_type);
+    let text = match output_type {
+        CmdOutputType::Json => serde_json::to_string_pretty(&document)? + "\n",
+        CmdOutputType::Yaml => serde_yaml::to_string(&document)?,
+        CmdOutputType::Toml => toml::to_string(&document)?,
+    };
+    writer.write_all(text.as_bytes())?;
+
+    Ok(())
+}
+
+/// Serializes a rebuilt document, with its datetimes written as TOML datetimes, or as strings
+/// for formats that don't have them. Likewise, keys that aren't strings are only written as
+/// such in YAML.
+struct Document<'a> {
+    node: &'a Node,
+    output_type: CmdOutputType,
+}
+
+impl<'a> Document<'a> {
+    fn new(node: &'a Node, output_type: CmdOutputType) -> Self {
+        Self { node, output_type }
+    }
+}
+
+struct DocumentKey<'a> {
+    key: &'a Key,
+    output_type: CmdOutputType,
+}
+
+impl Serialize for DocumentKey<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self.key {
+            Key::Other(k) if self.output_type == CmdOutputType::Yaml => {
+                match serde_yaml::from_str::<Node>(k) {
+                    Ok(key) => Document::new(&key, self.output_type).serialize(serializer),
+                    Err(_) => serializer.serialize_str(k),
+                }
+            }
+            key => serializer.serialize_str(key.as_str()),
+        }
+    }
+}
+
+impl Serialize for Document<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self.node {
+            Node::Null => serializer.serialize_unit(),
+            Node::Bool(b) => serializer.serialize_bool(*b),
+            Node::Number(n) => serialize_number(serializer, n, self.output_type),
+            Node::String(s) => serializer.serialize_str(s),
+            Node::DateTime(d) if self.output_type == CmdOutputType::Toml => d.serialize(serializer),
+            Node::DateTime(d) => serializer.serialize_str(&d.to_string()),
+            // `serde_yaml` can only write local tags, so `!!binary` is lost in all the formats.
+            Node::Binary(b) => serializer.serialize_str(&encode_binary(b)),
+            Node::Tagged(tag, v) if self.output_type == CmdOutputType::Yaml => {
+                serialize_tagged(serializer, tag, &Document::new(v, self.output_type))
+            }
+            Node::Tagged(_, v) | Node::Merged(v) => {
+                Document::new(v, self.output_type).serialize(serializer)
+            }
+            Node::Alias(alias) => serializer.serialize_str(&format!("*{}", alias.name)),
+            Node::Array(a) => {
+                serializer.collect_seq(a.iter().map(|v| Document::new(v, self.output_type)))
+            }
+            Node::Object(o) => serializer.collect_map(o.iter().map(|(k, v)| {
+                let key = DocumentKey {
+                    key: k,
+                    output_type: self.output_type,
+                };
+                (key, Document::new(v, self.output_type))
+            })),
+        }
+    }
+}
+
+fn serialize_number<S: Serializer>(
+    serializer: S,
+    n: &Number,
+    output_type: CmdOutputType,
+) -> std::result::Result<S::Ok, S::Error> {
+    match n {
+        Number::Int(i) => serializer.serialize_i64(*i),
+        Number::UInt(u) => serializer.serialize_u64(*u),
+        // serde_json would write them as null.
+        Number::Float(x) if !x.is_finite() && output_type == CmdOutputType::Json => {
+            Err(S::Error::custom(format!("{} can't be written in JSON", n)))
+        }
+        Number::Float(x) => serializer.serialize_f64(*x),
+        // JSON numbers are written as they are. Literals that aren't valid JSON, e.g. `0x1f` from
+        // YAML, are written from their value.
+        Number::Literal(text, value) if output_type == CmdOutputType::Json => {
+            match serde_json::value::RawValue::from_string(text.clone()) {
+                Ok(raw) => raw.serialize(serializer),
+                Err(_) => serialize_number(serializer, value, output_type),
+            }
+        }
+        // serde_yaml writes integers of up to 128 bits, unlike TOML.
+        Number::Literal(text, _) if output_type == CmdOutputType::Yaml => match text.parse() {
+            Ok(i) => serializer.serialize_i128(i),
+            Err(_) => serialize_literal_value(serializer, n, output_type),
+        },
+        Number::Literal(..) => serialize_literal_value(serializer, n, output_type),
+    }
+}
+
+/// Serializes the value of a literal, for formats in which it can't be written as it is. Decimal
+/// literals must have the same value, rather than be rounded to a float.
+fn serialize_literal_value<S: Serializer>(
+    serializer: S,
+    n: &Number,
+    output_type: CmdOutputType,
+) -> std::result::Result<S::Ok, S::Error> {
+    let literal = decimal_digits(&n.to_string());
+    if literal.is_some() && literal != decimal_digits(&n.value().to_string()) {
+        let format = match output_type {
+            CmdOutputType::Json => "JSON",
+            CmdOutputType::Yaml => "YAML",
+            CmdOutputType::Toml => "TOML",
+        };
+        return Err(S::Error::custom(format!(
+            "{} can't be written in {} without losing precision",
+            n, format
+        )));
+    }
+
+    serialize_number(serializer, n.value(), output_type)
+}
+
+/// Splits a decimal number into its sign, its significant digits and the exponent of its last
+/// digit, e.g. `-1.50e3` into `(true, "15", 2)`, to tell whether two texts are the same number.
+/// Returns `None` for other texts, e.g. `0x1f` or `inf`.
+fn decimal_digits(text: &str) -> Option<(bool, String, i64)> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(t) => (true, t),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+        Some((m, e)) => (m, e.strip_prefix('+').unwrap_or(e).parse::<i64>().ok()?),
+        None => (text, 0),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if integer.is_empty() && fraction.is_empty()
+        || !integer
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let digits = format!("{}{}", integer, fraction);
+    let digits = digits.trim_start_matches('0');
+    let significant = digits.trim_end_matches('0');
+    if significant.is_empty() {
+        return Some((false, String::new(), 0));
+    }
+    let exponent = exponent
+        .checked_sub(fraction.len() as i64)?
+        .checked_add((digits.len() - significant.len()) as i64)?;
+
+    Some((negative, significant.to_string(), exponent))
+}
+
+/// Serializes a tagged YAML value, the way `serde_yaml` does for its own tagged values: as a map
+/// with a single entry, whose key is the tag.
+fn serialize_tagged<S: Serializer>(
+    serializer: S,
+    tag: &str,
+    value: &impl Serialize,
+) -> std::result::Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(1))?;
+    map.serialize_entry(&YamlTag(tag), value)?;
+    map.end()
+}
+
+/// `serde_yaml` recognizes a tag from the way it is formatted: a `!` on its own, then the tag.
+struct YamlTag<'a>(&'a str);
+
+impl Display for YamlTag<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("!")?;
+        f.write_str(self.0)
+    }
+}
+
+impl Serialize for YamlTag<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+fn parse_line(
+    line: &str,
+    dialect: &dyn PathDialect,
+) -> std::result::Result<(Path, LineValue), String> {
+    let (path, rest) = dialect.parse(line)?;
+    let value = rest
+        .strip_prefix(" => ")
+        .ok_or_else(|| format!("expected ' => ' after the path, found {:?}", rest))?;
+
+    // Aliases are printed as `*name (see <path of the anchor>)`.
+    let anchor = value
+        .strip_prefix('*')
+        .and_then(|v| v.strip_suffix(')'))
+        .and_then(|v| v.split_once(" (see "));
+    if let Some((_, anchor)) = anchor {
+        return Ok((path, LineValue::Alias(parse_anchor(anchor, dialect)?)));
+    }
+
+    Ok((path, LineValue::Value(parse_value(value)?)))
+}
+
+/// Parses the path of an anchor, which is the whole text.
+fn parse_anchor(s: &str, dialect: &dyn PathDialect) -> std::result::Result<Path, String> {
+    match dialect.parse(s)? {
+        (anchor, "") => Ok(anchor),
+        (_, rest) => Err(format!(
+            "unexpected {:?} after the path of the anchor",
+            rest
+        )),
+    }
+}
+
+fn parse_gron_line(line: &str) -> std::result::Result<(Path, LineValue), String> {
+    let dialect = GronDialect(Identifiers::Unicode);
+    let (path, rest) = dialect.parse(line)?;
+    let value = rest
+        .strip_prefix(" = ")
+        .and_then(|v| v.trim_end().strip_suffix(';'))
+        .ok_or_else(|| format!("expected ' = <value>;' after the path, found {:?}", rest))?;
+
+    // Aliases are assigned the path of their anchor, e.g. `json.a = json.b;`.
+    if value.starts_with("json") {
+        return Ok((path, LineValue::Alias(parse_anchor(value, &dialect)?)));
+    }
+
+    let value = match value {
+        "NaN" => Node::Number(Number::Float(f64::NAN)),
+        "Infinity" => Node::Number(Number::Float(f64::INFINITY)),
+        "-Infinity" => Node::Number(Number::Float(f64::NEG_INFINITY)),
+        value => match value.parse() {
+            Ok(node) => node,
+            // Numbers of YAML inputs are written as they are, e.g. `0x1f`, which is also valid
+            // JavaScript.
+            Err(_) => yaml::parse_number(value)
+                .map(Node::Number)
+                .ok_or_else(|| format!("invalid value {:?}", value))?,
+        },
+    };
+
+    Ok((path, LineValue::Value(value)))
+}
+
+fn parse_value(s: &str) -> std::result::Result<Node, String> {
+    match s {
+        "null" => Ok(Node::Null),
+        "true" => Ok(Node::Bool(true)),
+        "false" => Ok(Node::Bool(false)),
+        "inf" => Ok(Node::Number(Number::Float(f64::INFINITY))),
+        "-inf" => Ok(Node::Number(Number::Float(f64::NEG_INFINITY))),
+        "nan" => Ok(Node::Number(Number::Float(f64::NAN))),
+        "{}" => Ok(Node::Object(Mapping::new())),
+        "[]" => Ok(Node::Array(Vec::new())),
+        // Containers collapsed by `--max-depth` are compact JSON, unlike their `--summarize` form.
+        s if s.starts_with(['{', '[']) => s.parse().map_err(|_| {
+            if is_summary(s) {
+                format!("{:?} is a summary, which can't be read back", s)
+            } else {
+                format!("invalid value {:?}", s)
+            }
+        }),
+        // The tag of a container is on a line of its own, before the values of the container.
+        s if s.starts_with('!') => match s.split_once(' ') {
+            Some(("!!binary", value)) => match parse_value(value)? {
+                Node::String(b) => decode_binary(&b)
+                    .map(Node::Binary)
+                    .map_err(|_| format!("invalid binary value {:?}", value)),
+                _ => Err(format!("invalid binary value {:?}", value)),
+            },
+            Some((tag, value)) => Ok(Node::Tagged(tag.to_string(), Box::new(parse_value(value)?))),
+            None => Ok(Node::Tagged(s.to_string(), Box::new(Node::Null))),
+        },
+        // Older versions only escaped newlines, which still reads back as the same string.
+        s if s.len() > 1 && s.starts_with('"') && s.ends_with('"') => {
+            Ok(Node::String(serde_json::from_str(s).unwrap_or_else(|_| {
+                s[1..s.len() - 1].replace("\\n", "\n")
+            })))
+        }
+        s => match s.parse::<Number>().ok().or_else(|| yaml::parse_number(s)) {
+            Some(n) => Ok(Node::Number(n)),
+            None => s
+                .parse::<Datetime>()
+                .map(Node::DateTime)
+                .map_err(|_| format!("invalid value {:?}", s)),
+        },
+    }
+}
+
+/// Whether a value is the summary of a collapsed container, e.g. `{2 keys}` or `[1 item]`.
+fn is_summary(s: &str) -> bool {
+    let summary = |s: &str, unit: &str| {
+        s.split_once(' ').is_some_and(|(count, rest)| {
+            count.chars().all(|c| c.is_ascii_digit())
+                && (rest == unit || rest.strip_prefix(unit) == Some("s"))
+        })
+    };
+
+    s.strip_prefix('{')
+        .and_then(|s| s.strip_suffix('}'))
+        .is_some_and(|s| summary(s, "key"))
+        || s.strip_prefix('[')
+            .and_then(|s| s.strip_suffix(']'))
+            .is_some_and(|s| summary(s, "item"))
+}
+
+fn insert_value(root: &mut Node, path: &Path, value: Node) -> std::result::Result<(), String> {
+    let mut current = root;
+
+    for segment in path.iter() {
+        current = untagged(current);
+        current = match segment {
+            Segment::Key(k) | Segment::OtherKey(k) => {
+                if current.is_null() {
+                    *current = Node::Object(Mapping::new());
+                }
+                let key = match segment {
+                    Segment::OtherKey(_) => Key::Other(k.clone()),
+                    _ => Key::String(k.clone()),
+                };
+                match current {
+                    Node::Object(o) => o.entry(key).or_insert(Node::Null),
+                    _ => return Err(format!("key {:?} used on a non-object value", k)),
+                }
+            }
+            Segment::Index(i) => {
+                if current.is_null() {
+                    *current = Node::Array(Vec::new());
+                }
+                match current {
+                    Node::Array(a) => {
+                        if a.len() <= *i {
+                            let len = i
+                                .checked_add(1)
+                                .filter(|len| len - a.len() <= MAX_INDEX_GAP)
+                                .ok_or_else(|| {
+                                    format!(
+                                        "index {} is too far past the end of an array of {} items",
+                                        i,
+                                        a.len()
+                                    )
+                                })?;
+                            a.resize(len, Node::Null);
+                        }
+                        &mut a[*i]
+                    }
+                    _ => return Err(format!("index {} used on a non-array value", i)),
+                }
+            }
+        };
+    }
+
+    match (&current, &value) {
+        (Node::Null, _) => *current = value,
+        // gron declares containers before their content.
+        (Node::Object(_), Node::Object(o)) if o.is_empty() => {}
+        (Node::Array(_), Node::Array(a)) if a.is_empty() => {}
+        _ => return Err("value is defined more than once".to_string()),
+    }
+
+    Ok(())
+}
+
+/// Returns the value of a tagged node, which holds the values below it.
+fn untagged(node: &mut Node) -> &mut Node {
+    match node {
+        Node::Tagged(_, value) => untagged(value),
+        node => node,
+    }
+}
+
+#[cfg(test)]
+mod test_parse_value {
+    use serde_json::json;
+
+    use super::parse_value;
+    use crate::document::{Node, Number};
+
+    #[test]
+    fn test_scalars() {
+        assert_eq!(parse_value("null"), Ok(Node::Null));
+        assert_eq!(parse_value("true"), Ok(Node::from(json!(true))));
+        assert_eq!(parse_value("false"), Ok(Node::from(json!(false))));
+        assert_eq!(parse_value("-12"), Ok(Node::from(json!(-12))));
+        assert_eq!(parse_value("1.5"), Ok(Node::from(json!(1.5))));
+        assert_eq!(
+            parse_value("-inf"),
+            Ok(Node::Number(Number::Float(f64::NEG_INFINITY)))
+        );
+        assert!(matches!(
+            parse_value("nan"),
+            Ok(Node::Number(Number::Float(x))) if x.is_nan()
+        ));
+    }
+
+    #[test]
+    fn test_number_literals() {
+        for literal in [
+            "1.50",
+            "1e3",
+            "0x1f",
+            "+1",
+            "123456789012345678901234567890",
+        ] {
+            match parse_value(literal) {
+                Ok(Node::Number(n)) => assert_eq!(n.to_string(), literal),
+                value => panic!("{:?} is not a number: {:?}", literal, value),
+            }
+        }
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(parse_value(r#""""#), Ok(Node::from(json!(""))));
+        assert_eq!(
+            parse_value(r#""a "b" c""#),
+            Ok(Node::from(json!("a \"b\" c")))
+        );
+        assert_eq!(parse_value(r#""a\nb""#), Ok(Node::from(json!("a\nb"))));
+        assert_eq!(
+            parse_value(r#""a \"b\"\t\\c\u001b""#),
+            Ok(Node::from(json!("a \"b\"\t\\c\u{1b}")))
+        );
+    }
+
+    #[test]
+    fn test_empty_containers() {
+        assert_eq!(parse_value("{}"), Ok(Node::from(json!({}))));
+        assert_eq!(parse_value("[]"), Ok(Node::from(json!([]))));
+    }
+
+    #[test]
+    fn test_collapsed_containers() {
+        assert_eq!(
+            parse_value(r#"{"a":[1,"nan",{}],"b":null}"#),
+            Ok(Node::from(json!({"a": [1, "nan", {}], "b": null})))
+        );
+        assert_eq!(parse_value("[[]]"), Ok(Node::from(json!([[]]))));
+
+        for summary in ["{1 key}", "{2 keys}", "[1 item]", "[10 items]"] {
+            let err = parse_value(summary).unwrap_err();
+            assert!(err.contains("summary"), "{}", err);
+        }
+        assert!(!parse_value("{a: 1}").unwrap_err().contains("summary"));
+    }
+
+    #[test]
+    fn test_datetimes() {
+        for datetime in [
+            "1979-05-27T07:32:00-08:00",
+            "1979-05-27T07:32:00",
+            "1979-05-27",
+            "07:32:00.999",
+        ] {
+            assert_eq!(
+                parse_value(datetime),
+                Ok(Node::DateTime(datetime.parse().unwrap()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_tags() {
+        assert_eq!(
+            parse_value("!Ref \"Bucket\""),
+            Ok(Node::Tagged(
+                "!Ref".to_string(),
+                Box::new(Node::from(json!("Bucket")))
+            ))
+        );
+        assert_eq!(
+            parse_value("!GetAtt"),
+            Ok(Node::Tagged("!GetAtt".to_string(), Box::new(Node::Null)))
+        );
+        assert_eq!(
+            parse_value("!!binary \"SGVsbG8=\""),
+            Ok(Node::Binary(b"Hello".to_vec()))
+        );
+        assert!(parse_value("!!binary \"%%\"").is_err());
+        assert!(parse_value("!Ref foo").is_err());
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        assert!(parse_value("foo").is_err());
+        assert!(parse_value("\"").is_err());
+    }
+}
+
+#[cfg(test)]
+mod test_unflatten {
+    use serde_json::json;
+
+    use super::{decimal_digits, unflatten, write_document};
+    use crate::document::Node;
+    use crate::path::{JqDialect, JsonPointerDialect};
+    use crate::CmdOutputType;
+
+    #[test]
+    fn test_rebuild_document() {
+        let lines = concat!(
+            ".address.city => \"London\"\n",
+            ".age => 43\n",
+            ".phones[0] => \"+44 1234567\"\n",
+            ".phones[1] => \"+44 2345678\"\n",
+            ".tags => []\n",
+            "\n",
+            ".\"first name\" => \"John\"\n",
+        );
+
+        assert_eq!(
+            unflatten(lines.as_bytes(), &JqDialect, false).unwrap(),
+            Node::from(json!({
+                "address": {"city": "London"},
+                "age": 43,
+                "phones": ["+44 1234567", "+44 2345678"],
+                "tags": [],
+                "first name": "John",
+            }))
+        );
+    }
+
+    #[test]
+    fn test_rebuild_scalar() {
+        assert_eq!(
+            unflatten(". => 42\n".as_bytes(), &JqDialect, false).unwrap(),
+            Node::from(json!(42))
+        );
+    }
+
+    #[test]
+    fn test_write_datetimes() {
+        let document = unflatten(
+            ".dob => 1979-05-27T07:32:00-08:00\n".as_bytes(),
+            &JqDialect,
+            false,
+        )
+        .unwrap();
+
+        let write = |output_type| {
+            let mut buffer = Vec::new();
+            write_document(&mut buffer, &document, output_type).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        assert_eq!(
+            write(CmdOutputType::Json),
+            "{\n  \"dob\": \"1979-05-27T07:32:00-08:00\"\n}\n"
+        );
+        assert_eq!(
+            write(CmdOutputType::Yaml),
+            "dob: 1979-05-27T07:32:00-08:00\n"
+        );
+        assert_eq!(
+            write(CmdOutputType::Toml),
+            "dob = 1979-05-27T07:32:00-08:00\n"
+        );
+    }
+
+    #[test]
+    fn test_write_special_floats() {
+        let lines = ".max => inf\n.min => -inf\n.none => nan\n.zero => -0.0\n";
+        let document = unflatten(lines.as_bytes(), &JqDialect, false).unwrap();
+
+        let write = |output_type| {
+            let mut buffer = Vec::new();
+            write_document(&mut buffer, &document, output_type)
+                .map(|_| String::from_utf8(buffer).unwrap())
+        };
+
+        assert_eq!(
+            write(CmdOutputType::Yaml).unwrap(),
+            "max: .inf\nmin: -.inf\nnone: .nan\nzero: -0.0\n"
+        );
+        assert_eq!(
+            write(CmdOutputType::Toml).unwrap(),
+            "max = inf\nmin = -inf\nnone = nan\nzero = -0.0\n"
+        );
+        // JSON has no such floats, they aren't written as null, and nothing is written.
+        assert!(write(CmdOutputType::Json).is_err());
+
+        let mut buffer = Vec::new();
+        assert!(write_document(&mut buffer, &document, CmdOutputType::Json).is_err());
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_write_number_literals() {
+        let lines = concat!(
+            ".price => 10.50\n",
+            ".id => 123456789012345678901234567890\n",
+            ".mask => 0xff\n",
+            ".big => 1E5\n",
+        );
+        let document = unflatten(lines.as_bytes(), &JqDialect, false).unwrap();
+
+        let write = |output_type| {
+            let mut buffer = Vec::new();
+            write_document(&mut buffer, &document, output_type).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        assert_eq!(
+            write(CmdOutputType::Json),
+            concat!(
+                "{\n",
+                "  \"price\": 10.50,\n",
+                "  \"id\": 123456789012345678901234567890,\n",
+                "  \"mask\": 255,\n",
+                "  \"big\": 1E5\n",
+                "}\n"
+            )
+        );
+        assert_eq!(
+            write(CmdOutputType::Yaml),
+            "price: 10.5\nid: 123456789012345678901234567890\nmask: 255\nbig: 100000.0\n"
+        );
+    }
+
+    #[test]
+    fn test_number_literals_are_not_rounded() {
+        for (line, output_type) in [
+            (
+                ".id => 123456789012345678901234567890\n",
+                CmdOutputType::Toml,
+            ),
+            (".pi => 3.141592653589793238\n", CmdOutputType::Yaml),
+            (".pi => 3.141592653589793238\n", CmdOutputType::Toml),
+            (".huge => 1e400\n", CmdOutputType::Yaml),
+        ] {
+            let document = unflatten(line.as_bytes(), &JqDialect, false).unwrap();
+
+            let mut buffer = Vec::new();
+            let err = write_document(&mut buffer, &document, output_type).unwrap_err();
+
+            assert!(
+                err.to_string().contains("without losing precision"),
+                "{}",
+                err
+            );
+            assert!(buffer.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_decimal_digits() {
+        assert_eq!(decimal_digits("-1.50e3"), Some((true, "15".to_string(), 2)));
+        assert_eq!(decimal_digits("1500.0"), decimal_digits("1.5E+3"));
+        assert_eq!(decimal_digits("+.5"), decimal_digits("0.5"));
+        assert_eq!(decimal_digits("0.000"), decimal_digits("-0"));
+        assert_ne!(decimal_digits("0.1"), decimal_digits("0.10000000000000001"));
+        assert_eq!(decimal_digits("0x1f"), None);
+        assert_eq!(decimal_digits("inf"), None);
+        assert_eq!(decimal_digits("."), None);
+    }
+
+    #[test]
+    fn test_write_tags() {
+        let lines = concat!(
+            ".name => !Sub \"${Env}-bucket\"\n",
+            ".arn => !GetAtt\n",
+            ".arn[0] => \"Bucket\"\n",
+            ".arn[1] => \"Arn\"\n",
+            ".data => !!binary \"SGVsbG8=\"\n",
+        );
+        let document = unflatten(lines.as_bytes(), &JqDialect, false).unwrap();
+
+        let write = |output_type| {
+            let mut buffer = Vec::new();
+            write_document(&mut buffer, &document, output_type).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        assert_eq!(
+            write(CmdOutputType::Yaml),
+            concat!(
+                "name: !Sub ${Env}-bucket\n",
+                "arn: !GetAtt\n",
+                "- Bucket\n",
+                "- Arn\n",
+                "data: SGVsbG8=\n",
+            )
+        );
+        assert_eq!(
+            write(CmdOutputType::Json),
+            concat!(
+                "{\n",
+                "  \"name\": \"${Env}-bucket\",\n",
+                "  \"arn\": [\n",
+                "    \"Bucket\",\n",
+                "    \"Arn\"\n",
+                "  ],\n",
+                "  \"data\": \"SGVsbG8=\"\n",
+                "}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_conflicting_paths_are_rejected() {
+        let err = unflatten(".foo => 1\n.foo[0] => 2\n".as_bytes(), &JqDialect, false).unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+
+        assert!(unflatten(".foo => 1\n.foo => 2\n".as_bytes(), &JqDialect, false).is_err());
+        assert!(unflatten(".[0] => 1\n.foo => 2\n".as_bytes(), &JqDialect, false).is_err());
+    }
+
+    #[test]
+    fn test_indices_far_past_the_end_are_rejected() {
+        for lines in [
+            ".a[18446744073709551615] => 1\n",
+            ".a[100000000000] => 1\n",
+            ".a[0] => 1\n.a[65538] => 2\n",
+        ] {
+            let err = unflatten(lines.as_bytes(), &JqDialect, false).unwrap_err();
+            assert!(err.to_string().contains("too far past the end"), "{}", err);
+        }
+
+        let node = unflatten(".a[2] => 1\n".as_bytes(), &JqDialect, false).unwrap();
+        assert_eq!(node, Node::from(json!({"a": [null, null, 1]})));
+    }
+
+    #[test]
+    fn test_rebuild_document_from_gron() {
+        let lines = concat!(
+            "json = {};\n",
+            "json.address = {};\n",
+            "json.address.city = \"London\";\n",
+            "json.phones = [];\n",
+            "json.phones[0] = \"+44 1234567\";\n",
+            "json.tags = [];\n",
+            "json[\"first name\"] = \"John \\\"Johnny\\\" Doe\";\n",
+        );
+
+        assert_eq!(
+            unflatten(lines.as_bytes(), &JqDialect, true).unwrap(),
+            Node::from(json!({
+                "address": {"city": "London"},
+                "phones": ["+44 1234567"],
+                "tags": [],
+                "first name": "John \"Johnny\" Doe",
+            }))
+        );
+    }
+
+    #[test]
+    fn test_rebuild_aliases() {
+        let lines = concat!(
+            ".defaults.tags[0] => \"linux\"\n",
+            ".jobs[0] => *defaults (see .defaults)\n",
+            ".jobs[1].tags[0] => *linux (see .defaults.tags[0])\n",
+        );
+        let gron_lines = concat!(
+            "json = {};\n",
+            "json.defaults = {};\n",
+            "json.defaults.tags = [];\n",
+            "json.defaults.tags[0] = \"linux\";\n",
+            "json.jobs = [];\n",
+            "json.jobs[0] = json.defaults;\n",
+            "json.jobs[1] = {};\n",
+            "json.jobs[1].tags = [];\n",
+            "json.jobs[1].tags[0] = json.defaults.tags[0];\n",
+        );
+        let expected = Node::from(json!({
+            "defaults": {"tags": ["linux"]},
+            "jobs": [{"tags": ["linux"]}, {"tags": ["linux"]}],
+        }));
+
+        assert_eq!(
+            unflatten(lines.as_bytes(), &JqDialect, false).unwrap(),
+            expected
+        );
+        assert_eq!(
+            unflatten(gron_lines.as_bytes(), &JqDialect, true).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_invalid_aliases_are_rejected() {
+        for lines in [
+            ".a => *b (see .b)\n",
+            ".a => 1\n.b => *a (see .a.c)\n",
+            ".a => 1\n.b => *a (see .a => 1)\n",
+        ] {
+            assert!(
+                unflatten(lines.as_bytes(), &JqDialect, false).is_err(),
+                "{}",
+                lines
+            );
+        }
+        assert!(unflatten("json.a = json.b;\n".as_bytes(), &JqDialect, true).is_err());
+    }
+
+    #[test]
+    fn test_alias_copies_are_limited() {
+        // Each line copies the whole document, doubling it.
+        let mut lines = ".[0] => 1\n".to_string();
+        for i in 1..64 {
+            lines.push_str(&format!(".[{}] => *a (see .)\n", i));
+        }
+
+        let err = unflatten(lines.as_bytes(), &JqDialect, false).unwrap_err();
+        assert!(
+            err.to_string().contains("repetition limit exceeded"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_invalid_gron_line_is_rejected() {
+        assert!(unflatten("json.foo = 1\n".as_bytes(), &JqDialect, true).is_err());
+        assert!(unflatten("json.foo = bar;\n".as_bytes(), &JqDialect, true).is_err());
+        assert!(unflatten(
+            "json.foo = 1;\njson.foo = {};\n".as_bytes(),
+            &JqDialect,
+            true
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_rebuild_document_with_dialect() {
+        let lines = concat!(
+            "/address/city => \"London\"\n",
+            "/phones/0 => \"+44 1234567\"\n",
+            "/first name => \"John\"\n",
+        );
+
+        assert_eq!(
+            unflatten(lines.as_bytes(), &JsonPointerDialect, false).unwrap(),
+            Node::from(json!({
+                "address": {"city": "London"},
+                "phones": ["+44 1234567"],
+                "first name": "John",
+            }))
+        );
+    }
+
+    #[test]
+    fn test_invalid_line_is_rejected() {
+        let err = unflatten(".foo => 1\n.bar 2\n".as_bytes(), &JqDialect, false).unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
+}
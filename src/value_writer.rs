@@ -1,29 +1,117 @@
+use std::cmp::Ordering;
 use std::io::Write;
-use std::str;
+use std::iter::Enumerate;
+use std::ops::Range;
+use std::{str, vec};
 
 use console::ConsoleWriter;
+use gron::GronWriter;
 use is_terminal::is_terminal;
-use serde_json::{Number, Value};
-use termcolor::ColorChoice;
+use regex::Regex;
+use termcolor::{Buffer, Color, ColorChoice, ColorSpec, WriteColor};
+use toml::value::Datetime;
 
+use crate::document::{encode_binary, Key, Node, Number};
 use crate::error::Result;
-use crate::CmdColor;
+use crate::filter::Filter;
+use crate::path::{to_json_string, Identifiers, Path, PathDialect, Segment};
+use crate::select::{Selection, Selector};
+use crate::{CmdColor, CmdSortKeys};
 
 mod console;
+mod gron;
+
+lazy_static! {
+    static ref JSON_NUMBER: Regex =
+        Regex::new(r"^-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][-+]?[0-9]+)?$").unwrap();
+    static ref KEY_COLOR_SPEC: ColorSpec = ColorSpec::new().set_fg(Some(Color::Blue)).clone();
+    static ref STRING_VALUE_COLOR_SPEC: ColorSpec =
+        ColorSpec::new().set_fg(Some(Color::Green)).clone();
+    static ref NULL_VALUE_COLOR_SPEC: ColorSpec = ColorSpec::new()
+        .set_fg(Some(Color::Black))
+        .set_bold(true)
+        .clone();
+    static ref RAW_VALUE_COLOR_SPEC: ColorSpec = ColorSpec::new().set_bold(true).clone();
+    static ref DATETIME_VALUE_COLOR_SPEC: ColorSpec =
+        ColorSpec::new().set_fg(Some(Color::Magenta)).clone();
+    static ref TAG_COLOR_SPEC: ColorSpec = ColorSpec::new().set_fg(Some(Color::Yellow)).clone();
+    static ref FILE_NAME_COLOR_SPEC: ColorSpec =
+        ColorSpec::new().set_fg(Some(Color::Magenta)).clone();
+    static ref MATCH_COLOR_SPEC: ColorSpec = ColorSpec::new()
+        .set_fg(Some(Color::Red))
+        .set_bold(true)
+        .clone();
+}
 
 #[cfg(test)]
 use mockall::{automock, predicate::*};
 
 #[cfg_attr(test, automock)]
 pub(super) trait ValueWriter {
-    fn write_string(&mut self, path: &str, value: &str) -> Result<()>;
-    fn write_number(&mut self, path: &str, value: &Number) -> Result<()>;
-    fn write_bool(&mut self, path: &str, value: bool) -> Result<()>;
-    fn write_null(&mut self, path: &str) -> Result<()>;
-    fn write_raw(&mut self, path: &str, value: &str) -> Result<()>;
+    fn write_string(&mut self, path: &Path, value: &str) -> Result<()>;
+    fn write_number(&mut self, path: &Path, value: &Number) -> Result<()>;
+    fn write_bool(&mut self, path: &Path, value: bool) -> Result<()>;
+    fn write_null(&mut self, path: &Path) -> Result<()>;
+    fn write_datetime(&mut self, path: &Path, value: &Datetime) -> Result<()>;
+    fn write_raw(&mut self, path: &Path, value: &str) -> Result<()>;
+    /// Writes the tag of the value at `path`, which is written next. Writers that show tags
+    /// write the tags of scalars on the same line as the value, and the tags of containers on a
+    /// line of their own.
+    fn write_tag(&mut self, path: &Path, tag: &str) -> Result<()>;
+    /// Writes a YAML alias of the anchor `name`, whose value is at `anchor`.
+    fn write_alias(&mut self, path: &Path, name: &str, anchor: &Path) -> Result<()>;
+    /// Writes a container on a single line instead of its values, as compact JSON or as a
+    /// summary of its size.
+    fn write_collapsed(&mut self, path: &Path, value: &Node) -> Result<()>;
+    /// Writes a marker in place of an excluded value at `path`, which has `leaves` values.
+    fn write_excluded(&mut self, path: &Path, leaves: usize) -> Result<()>;
+    /// Sets whether the values written next come from a YAML merge key.
+    fn set_merged(&mut self, merged: bool);
+    /// Sets the name of the file whose values are written next, which prefixes each line when
+    /// there is one, like in grep.
+    fn set_file(&mut self, file: Option<String>);
+    /// Number of values that the filter selected so far.
+    fn matches(&self) -> usize;
+    /// Whether as many values as asked were selected, so that the following ones are left out.
+    fn is_done(&self) -> bool;
+    /// Ends a document, writing what is still held back, e.g. the tag of a container whose values
+    /// were all left out.
+    fn flush(&mut self) -> Result<()>;
+}
+
+/// How the value writers print values.
+#[derive(Debug, Default)]
+pub(super) struct WriterOptions {
+    /// Whether numbers are printed from their value rather than as written in the input.
+    pub(super) normalize_numbers: bool,
+    /// Whether strings are printed as is, without quotes and escape sequences. gron output
+    /// always escapes them.
+    pub(super) raw_strings: bool,
+    /// The lines to print.
+    pub(super) filter: Filter,
+    /// Whether the values are only counted, without being printed.
+    pub(super) quiet: bool,
+    /// Number of values after which the following ones are left out.
+    pub(super) max_count: Option<usize>,
+    /// Whether collapsed containers are written as a summary of their size rather than as
+    /// compact JSON. gron output always writes compact JSON.
+    pub(super) summarize: bool,
 }
 
-pub(super) fn get_writer(writer: &mut dyn Write, color: CmdColor) -> Box<dyn ValueWriter + '_> {
+impl WriterOptions {
+    fn is_done(&self, matches: usize) -> bool {
+        self.max_count.is_some_and(|max| matches >= max)
+    }
+}
+
+pub(super) fn get_writer<'a>(
+    writer: &'a mut dyn Write,
+    color: CmdColor,
+    gron: bool,
+    dialect: &'static dyn PathDialect,
+    identifiers: Option<Identifiers>,
+    options: WriterOptions,
+) -> Box<dyn ValueWriter + 'a> {
     let color_choice = match color {
         CmdColor::Auto => {
             if is_terminal(std::io::stdout()) {
@@ -36,83 +124,521 @@ pub(super) fn get_writer(writer: &mut dyn Write, color: CmdColor) -> Box<dyn Val
         CmdColor::Never => ColorChoice::Never,
     };
 
-    Box::new(ConsoleWriter::new(writer, color_choice))
+    if gron {
+        // Like JSONPath, JavaScript reads Unicode identifiers.
+        let identifiers = identifiers.unwrap_or(Identifiers::Unicode);
+        Box::new(GronWriter::new(writer, color_choice, identifiers, options))
+    } else {
+        Box::new(ConsoleWriter::new(writer, color_choice, dialect, options))
+    }
 }
 
-pub(super) fn print_value(path: &str, value: Value, writer: &mut dyn ValueWriter) -> Result<()> {
-    match value {
-        Value::Object(o) => {
-            if !o.is_empty() {
-                let prefix = format!("{}{}", path, if path.ends_with('.') { "" } else { "." });
+/// Writes a line made of parts in their own color, unless the filter leaves it out or the output
+/// is quiet. `path` and `value` are the indices of the parts holding the path and the value. The
+/// spans matched by the filter are highlighted, and the line ends within the color of what is
+/// written last. The line is prefixed with the name of the file, if any, which the filter doesn't
+/// see. Returns whether the filter selected the line.
+fn write_line_parts(
+    buffer: &mut Buffer,
+    options: &WriterOptions,
+    file: Option<&str>,
+    parts: &[(&str, Option<&ColorSpec>)],
+    path: usize,
+    value: usize,
+) -> Result<bool> {
+    let line: String = parts.iter().map(|(text, _)| *text).collect();
+    let span = |index: usize| {
+        let start = parts[..index]
+            .iter()
+            .map(|(text, _)| text.len())
+            .sum::<usize>();
+        start..start + parts[index].0.len()
+    };
+
+    let highlights = match options.filter.find(&line, span(path), span(value)) {
+        Some(_) if options.quiet => return Ok(true),
+        Some(highlights) => highlights,
+        None => return Ok(false),
+    };
+
+    if let Some(file) = file {
+        buffer.set_color(&FILE_NAME_COLOR_SPEC)?;
+        write!(buffer, "{}", file)?;
+        buffer.reset()?;
+        write!(buffer, ":")?;
+    }
+
+    let mut chunks: Vec<(Range<usize>, Option<&ColorSpec>)> = Vec::new();
+    for (i, (_, spec)) in parts.iter().enumerate() {
+        let Range { mut start, end } = span(i);
 
-                for (k, v) in o {
-                    print_value(&format!("{}{}", prefix, escape_path_element(k)), v, writer)?
+        for h in &highlights {
+            let (h_start, h_end) = (h.start.max(start), h.end.min(end));
+            if h_start < h_end {
+                if start < h_start {
+                    chunks.push((start..h_start, *spec));
                 }
-            } else {
-                writer.write_raw(path, "{}")?
+                chunks.push((h_start..h_end, Some(&MATCH_COLOR_SPEC)));
+                start = h_end;
+            }
+        }
+        if start < end {
+            chunks.push((start..end, *spec));
+        }
+    }
+
+    if chunks.is_empty() {
+        writeln!(buffer)?;
+    }
+    for (i, (range, spec)) in chunks.iter().enumerate() {
+        let end = if i == chunks.len() - 1 { "\n" } else { "" };
+
+        if let Some(spec) = spec {
+            buffer.set_color(spec)?;
+            write!(buffer, "{}{}", &line[range.clone()], end)?;
+            buffer.reset()?;
+        } else {
+            write!(buffer, "{}{}", &line[range.clone()], end)?;
+        }
+    }
+
+    Ok(true)
+}
+
+/// A container being printed, with the children that are left to print.
+enum Frame {
+    Object(vec::IntoIter<(Key, Node)>),
+    Array(Enumerate<vec::IntoIter<Node>>),
+}
+
+impl Frame {
+    fn next_child(&mut self) -> Option<(Segment, Node)> {
+        match self {
+            Frame::Object(o) => o.next().map(|(k, v)| (Segment::from(k), v)),
+            Frame::Array(a) => a.next().map(|(i, v)| (Segment::Index(i), v)),
+        }
+    }
+}
+
+/// What `print_value` prints of a value, and in which order.
+#[derive(Debug, Default)]
+pub(super) struct PrintOptions {
+    pub(super) sort_keys: Option<CmdSortKeys>,
+    /// The subtrees to print, and the ones to leave out.
+    pub(super) selector: Selector,
+    /// Whether excluded subtrees are replaced by a marker with their number of values.
+    pub(super) mark_excluded: bool,
+    /// Depth from which containers are collapsed on a single line instead of being expanded.
+    pub(super) max_depth: Option<usize>,
+}
+
+/// Prints all the scalar values and empty containers of `value` that are selected.
+///
+/// The document is walked with an explicit stack rather than recursively, so that the depth of
+/// the document isn't limited by the size of the call stack. Children are moved out of their
+/// container as they are printed, which also means that the document is never dropped
+/// recursively.
+pub(super) fn print_value(
+    path: &mut Path,
+    value: Node,
+    writer: &mut dyn ValueWriter,
+    options: &PrintOptions,
+) -> Result<()> {
+    let mut stack: Vec<Frame> = Vec::new();
+    // The depth of the stack at the value from a merge key that is being printed, if any.
+    let mut merged_depth: Option<usize> = None;
+    // The depth of the stack at the selected value that is being printed, if any. The values
+    // outside of it are only traversed to find the selected ones.
+    let mut selected_depth = match options.selector.select(path.segments()) {
+        Selection::Selected => Some(0),
+        Selection::Partial => None,
+        Selection::Unselected => {
+            drop_value(value);
+            return Ok(());
+        }
+    };
+    if options.selector.is_excluded(path.segments()) {
+        if options.mark_excluded && selected_depth.is_some() {
+            writer.write_excluded(path, count_leaves(&value))?;
+        }
+        drop_value(value);
+        return Ok(());
+    }
+
+    let mut next = Some(value);
+    while let Some(value) = next.take() {
+        match value {
+            value @ (Node::Object(_) | Node::Array(_))
+                if selected_depth.is_some()
+                    && options
+                        .max_depth
+                        .is_some_and(|depth| path.segments().len() >= depth) =>
+            {
+                writer.write_collapsed(path, &value)?;
+                drop_value(value);
+                if !stack.is_empty() {
+                    path.pop();
+                }
+            }
+            Node::Object(o) if !o.is_empty() => {
+                let mut entries: Vec<_> = o.into_iter().collect();
+                match options.sort_keys {
+                    Some(CmdSortKeys::Lexicographic) => {
+                        entries.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()))
+                    }
+                    Some(CmdSortKeys::Natural) => {
+                        entries.sort_by(|a, b| natural_cmp(a.0.as_str(), b.0.as_str()))
+                    }
+                    None => {}
+                }
+                stack.push(Frame::Object(entries.into_iter()))
+            }
+            Node::Array(a) if !a.is_empty() => stack.push(Frame::Array(a.into_iter().enumerate())),
+            // The tagged value is printed next, at the same path.
+            Node::Tagged(tag, value) => {
+                if selected_depth.is_some() {
+                    writer.write_tag(path, &tag)?;
+                }
+                next = Some(*value);
+                continue;
+            }
+            Node::Merged(value) => {
+                if merged_depth.is_none() {
+                    merged_depth = Some(stack.len());
+                    writer.set_merged(true);
+                }
+                next = Some(*value);
+                continue;
+            }
+            _ if selected_depth.is_none() => {
+                if !stack.is_empty() {
+                    path.pop();
+                }
+            }
+            value => {
+                match value {
+                    Node::Object(_) => writer.write_raw(path, "{}")?,
+                    Node::Array(_) => writer.write_raw(path, "[]")?,
+                    Node::String(s) => writer.write_string(path, &s)?,
+                    Node::Number(n) => writer.write_number(path, &n)?,
+                    Node::Bool(b) => writer.write_bool(path, b)?,
+                    Node::DateTime(d) => writer.write_datetime(path, &d)?,
+                    Node::Binary(b) => {
+                        writer.write_tag(path, "!!binary")?;
+                        writer.write_string(path, &encode_binary(&b))?
+                    }
+                    Node::Tagged(..) | Node::Merged(_) => unreachable!("written above"),
+                    Node::Alias(alias) => {
+                        writer.write_alias(path, &alias.name, &path.with_segments(alias.anchor))?
+                    }
+                    Node::Null => writer.write_null(path)?,
+                }
+
+                // Only the root value has no segment of its own.
+                if !stack.is_empty() {
+                    path.pop();
+                }
+            }
+        }
+
+        // Moves to the next child, leaving the containers that have been fully printed.
+        loop {
+            if merged_depth.is_some_and(|depth| stack.len() <= depth) {
+                merged_depth = None;
+                writer.set_merged(false);
+            }
+            if selected_depth.is_some_and(|depth| stack.len() <= depth) {
+                selected_depth = None;
+            }
+
+            let frame = match stack.last_mut() {
+                Some(frame) => frame,
+                None => break,
             };
+            if let Some((segment, child)) = frame.next_child() {
+                path.push(segment);
+                if selected_depth.is_none() {
+                    match options.selector.select(path.segments()) {
+                        Selection::Selected => selected_depth = Some(stack.len()),
+                        Selection::Partial => {}
+                        // Skips the whole subtree.
+                        Selection::Unselected => {
+                            drop_value(child);
+                            path.pop();
+                            continue;
+                        }
+                    }
+                }
+                if options.selector.is_excluded(path.segments()) {
+                    if options.mark_excluded && selected_depth.is_some() {
+                        writer.write_excluded(path, count_leaves(&child))?;
+                    }
+                    drop_value(child);
+                    path.pop();
+                    continue;
+                }
+                next = Some(child);
+                break;
+            }
+
+            stack.pop();
+            if !stack.is_empty() {
+                path.pop();
+            }
+        }
+    }
+
+    Ok(())
+}
 
-            Ok(())
+/// Drops a value that is skipped whole. The nested values are taken apart with an explicit
+/// stack, since dropping them in place would recurse as deep as the value.
+fn drop_value(value: Node) {
+    let mut stack = vec![value];
+    while let Some(value) = stack.pop() {
+        match value {
+            Node::Object(o) => stack.extend(o.into_values()),
+            Node::Array(a) => stack.extend(a),
+            Node::Tagged(_, value) | Node::Merged(value) => stack.push(*value),
+            _ => {}
         }
-        Value::Array(a) => {
-            if !a.is_empty() {
-                for (i, v) in a.into_iter().enumerate() {
-                    print_value(&format!("{}[{}]", path, i), v, writer)?
+    }
+}
+
+/// Returns the text of a collapsed container: compact JSON, or its number of keys or items.
+/// Numbers are written from their value with `normalize_numbers`, like the values of the writer.
+fn collapsed_value(value: &Node, summarize: bool, normalize_numbers: bool) -> String {
+    match value {
+        _ if !summarize => to_compact_json(value, normalize_numbers),
+        Node::Object(o) if o.is_empty() => "{}".to_string(),
+        Node::Object(o) if o.len() == 1 => "{1 key}".to_string(),
+        Node::Object(o) => format!("{{{} keys}}", o.len()),
+        Node::Array(a) if a.is_empty() => "[]".to_string(),
+        Node::Array(a) if a.len() == 1 => "[1 item]".to_string(),
+        Node::Array(a) => format!("[{} items]", a.len()),
+        value => to_compact_json(value, normalize_numbers),
+    }
+}
+
+/// A container being written as compact JSON, with the children that are left to write, and
+/// whether any was written.
+enum CompactFrame<'a> {
+    Object(indexmap::map::Iter<'a, Key, Node>, bool),
+    Array(std::slice::Iter<'a, Node>, bool),
+}
+
+/// Writes a value as JSON on a single line. Like `print_value`, the value is walked with an
+/// explicit stack, so that its depth isn't limited by the size of the call stack. Tags are left
+/// out, and the scalars that JSON doesn't have are written as strings: datetimes, binary values,
+/// aliases (`"*name"`), NaN and infinities (`"nan"`, `"inf"` and `"-inf"`).
+fn to_compact_json(value: &Node, normalize_numbers: bool) -> String {
+    let mut out = String::new();
+    let mut stack: Vec<CompactFrame> = Vec::new();
+    let mut next = Some(value);
+
+    while let Some(mut value) = next.take() {
+        while let Node::Tagged(_, v) | Node::Merged(v) = value {
+            value = v;
+        }
+
+        match value {
+            Node::Object(o) => {
+                out.push('{');
+                stack.push(CompactFrame::Object(o.iter(), false));
+            }
+            Node::Array(a) => {
+                out.push('[');
+                stack.push(CompactFrame::Array(a.iter(), false));
+            }
+            Node::Null => out.push_str("null"),
+            Node::Bool(b) => out.push_str(&b.to_string()),
+            Node::Number(n) => out.push_str(&compact_json_number(n, normalize_numbers)),
+            Node::String(s) => out.push_str(&to_json_string(s)),
+            Node::DateTime(d) => out.push_str(&to_json_string(&d.to_string())),
+            Node::Binary(b) => out.push_str(&to_json_string(&encode_binary(b))),
+            Node::Alias(alias) => out.push_str(&to_json_string(&format!("*{}", alias.name))),
+            Node::Tagged(..) | Node::Merged(_) => unreachable!("unwrapped above"),
+        }
+
+        // Moves to the next child, closing the containers that have been fully written.
+        while let Some(frame) = stack.last_mut() {
+            let (child, written) = match frame {
+                CompactFrame::Object(o, written) => (o.next().map(|(k, v)| (Some(k), v)), written),
+                CompactFrame::Array(a, written) => (a.next().map(|v| (None, v)), written),
+            };
+
+            match child {
+                Some((key, child)) => {
+                    if std::mem::replace(written, true) {
+                        out.push(',');
+                    }
+                    if let Some(key) = key {
+                        out.push_str(&to_json_string(key.as_str()));
+                        out.push(':');
+                    }
+                    next = Some(child);
+                    break;
+                }
+                None => {
+                    out.push(match frame {
+                        CompactFrame::Object(..) => '}',
+                        CompactFrame::Array(..) => ']',
+                    });
+                    stack.pop();
                 }
+            }
+        }
+    }
+
+    out
+}
+
+/// Writes a number as JSON. Literals that aren't JSON numbers, e.g. `0x1f` from YAML, are written
+/// from their value.
+fn compact_json_number(n: &Number, normalize_numbers: bool) -> String {
+    match n.value() {
+        Number::Float(x) if !x.is_finite() => to_json_string(&n.value().to_string()),
+        value if normalize_numbers => value.to_string(),
+        value => {
+            let text = n.to_string();
+            if JSON_NUMBER.is_match(&text) {
+                text
             } else {
-                writer.write_raw(path, "[]")?
-            };
+                value.to_string()
+            }
+        }
+    }
+}
 
-            Ok(())
+/// Counts the values that `print_value` would print of `value`.
+fn count_leaves(value: &Node) -> usize {
+    let mut stack = vec![value];
+    let mut leaves = 0;
+
+    while let Some(value) = stack.pop() {
+        match value {
+            Node::Object(o) if !o.is_empty() => stack.extend(o.values()),
+            Node::Array(a) if !a.is_empty() => stack.extend(a),
+            Node::Tagged(_, value) | Node::Merged(value) => stack.push(value),
+            _ => leaves += 1,
         }
-        Value::String(s) => writer.write_string(path, &s),
-        Value::Number(n) => writer.write_number(path, &n),
-        Value::Bool(b) => writer.write_bool(path, b),
-        Value::Null => writer.write_null(path),
     }
+
+    leaves
 }
 
-fn escape_str_value(value: &str) -> String {
-    format!("\"{}\"", value.replace('\n', "\\n"))
+/// Compares keys by their text, except for runs of digits which are compared by value, so that
+/// `item2` comes before `item10`. Keys that compare equal this way, e.g. `a1` and `a01`, are
+/// ordered lexicographically.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut x, mut y) = (a, b);
+
+    loop {
+        let (cx, cy) = match (x.chars().next(), y.chars().next()) {
+            (Some(cx), Some(cy)) => (cx, cy),
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+        };
+
+        if cx.is_ascii_digit() && cy.is_ascii_digit() {
+            let (nx, rx) = split_digits(x);
+            let (ny, ry) = split_digits(y);
+            let (nx, ny) = (nx.trim_start_matches('0'), ny.trim_start_matches('0'));
+
+            match nx.len().cmp(&ny.len()).then_with(|| nx.cmp(ny)) {
+                Ordering::Equal => {}
+                ordering => return ordering,
+            }
+            x = rx;
+            y = ry;
+        } else {
+            match cx.cmp(&cy) {
+                Ordering::Equal => {}
+                ordering => return ordering,
+            }
+            x = &x[cx.len_utf8()..];
+            y = &y[cy.len_utf8()..];
+        }
+    }
 }
 
-fn escape_path_element(p: String) -> String {
-    let p = p.replace('"', "\\\"");
+fn split_digits(s: &str) -> (&str, &str) {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s.split_at(end)
+}
 
-    if p.chars().any(|c| !c.is_ascii_alphanumeric() && c != '_')
-        || p.chars().all(|c| c.is_numeric())
-    {
-        format!("\"{}\"", p)
-    } else {
-        p
+#[cfg(test)]
+mod test_natural_cmp {
+    use std::cmp::Ordering;
+
+    use super::natural_cmp;
+
+    #[test]
+    fn test_numbers_are_compared_by_value() {
+        assert_eq!(natural_cmp("item2", "item10"), Ordering::Less);
+        assert_eq!(natural_cmp("item10", "item2"), Ordering::Greater);
+        assert_eq!(natural_cmp("a2b10", "a2b9"), Ordering::Greater);
+        assert_eq!(natural_cmp("10", "9"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_text_is_compared_lexicographically() {
+        assert_eq!(natural_cmp("abc", "abd"), Ordering::Less);
+        assert_eq!(natural_cmp("ab", "abc"), Ordering::Less);
+        assert_eq!(natural_cmp("item", "item1"), Ordering::Less);
+        assert_eq!(natural_cmp("item1", "itemA"), Ordering::Less);
+        assert_eq!(natural_cmp("é1", "é10"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_leading_zeros() {
+        assert_eq!(natural_cmp("a01", "a2"), Ordering::Less);
+        assert_eq!(natural_cmp("a01", "a1"), Ordering::Less);
+        assert_eq!(natural_cmp("a1", "a1"), Ordering::Equal);
     }
 }
 
 #[cfg(test)]
 mod test_print_value {
-    use serde_json::{Map, Number, Value};
+    use std::str::FromStr;
 
     use super::*;
+    use crate::document::Mapping;
+    use crate::path::JqDialect;
+
+    fn path(s: &str) -> Path {
+        JqDialect.parse(s).unwrap().0
+    }
 
     #[test]
     fn test_print_object() {
         let mut writer = MockValueWriter::new();
         writer
             .expect_write_string()
-            .with(eq(".foo"), eq("bar"))
+            .with(eq(path(".foo")), eq("bar"))
             .times(1)
             .returning(|_, _| Ok(()));
         writer
             .expect_write_null()
-            .with(eq(".baz"))
+            .with(eq(path(".baz")))
             .times(1)
             .returning(|_| Ok(()));
 
-        let mut map = Map::new();
-        map.insert("foo".to_string(), Value::String("bar".to_string()));
-        map.insert("baz".to_string(), Value::Null);
+        let mut map = Mapping::new();
+        map.insert(
+            Key::String("foo".to_string()),
+            Node::String("bar".to_string()),
+        );
+        map.insert(Key::String("baz".to_string()), Node::Null);
 
-        print_value(".", Value::Object(map), &mut writer).unwrap();
+        print_value(
+            &mut Path::default(),
+            Node::Object(map),
+            &mut writer,
+            &PrintOptions::default(),
+        )
+        .unwrap();
     }
 
     #[test]
@@ -120,27 +646,33 @@ mod test_print_value {
         let mut writer = MockValueWriter::new();
         writer
             .expect_write_string()
-            .with(eq(".[0]"), eq("foo"))
+            .with(eq(path(".[0]")), eq("foo"))
             .times(1)
             .returning(|_, _| Ok(()));
         writer
             .expect_write_number()
-            .with(eq(".[1]"), eq(Number::from(0)))
+            .with(eq(path(".[1]")), eq(Number::UInt(0)))
             .times(1)
             .returning(|_, _| Ok(()));
         writer
             .expect_write_bool()
-            .with(eq(".[2]"), eq(true))
+            .with(eq(path(".[2]")), eq(true))
             .times(1)
             .returning(|_, _| Ok(()));
 
         let arr = vec![
-            Value::String("foo".to_string()),
-            Value::Number(Number::from(0)),
-            Value::Bool(true),
+            Node::String("foo".to_string()),
+            Node::Number(Number::UInt(0)),
+            Node::Bool(true),
         ];
 
-        print_value(".", Value::Array(arr), &mut writer).unwrap();
+        print_value(
+            &mut Path::default(),
+            Node::Array(arr),
+            &mut writer,
+            &PrintOptions::default(),
+        )
+        .unwrap();
     }
 
     #[test]
@@ -148,11 +680,17 @@ mod test_print_value {
         let mut writer = MockValueWriter::new();
         writer
             .expect_write_string()
-            .with(eq("foo"), eq("bar"))
+            .with(eq(path(".foo")), eq("bar"))
             .times(1)
             .returning(|_, _| Ok(()));
 
-        print_value("foo", Value::String("bar".to_string()), &mut writer).unwrap();
+        print_value(
+            &mut path(".foo"),
+            Node::String("bar".to_string()),
+            &mut writer,
+            &PrintOptions::default(),
+        )
+        .unwrap();
     }
 
     #[test]
@@ -160,11 +698,17 @@ mod test_print_value {
         let mut writer = MockValueWriter::new();
         writer
             .expect_write_number()
-            .with(eq("foo"), eq(Number::from(69)))
+            .with(eq(path(".foo")), eq(Number::UInt(69)))
             .times(1)
             .returning(|_, _| Ok(()));
 
-        print_value("foo", Value::Number(Number::from(69)), &mut writer).unwrap();
+        print_value(
+            &mut path(".foo"),
+            Node::Number(Number::UInt(69)),
+            &mut writer,
+            &PrintOptions::default(),
+        )
+        .unwrap();
     }
 
     #[test]
@@ -172,11 +716,17 @@ mod test_print_value {
         let mut writer = MockValueWriter::new();
         writer
             .expect_write_bool()
-            .with(eq("foo"), eq(true))
+            .with(eq(path(".foo")), eq(true))
             .times(1)
             .returning(|_, _| Ok(()));
 
-        print_value("foo", Value::Bool(true), &mut writer).unwrap();
+        print_value(
+            &mut path(".foo"),
+            Node::Bool(true),
+            &mut writer,
+            &PrintOptions::default(),
+        )
+        .unwrap();
     }
 
     #[test]
@@ -184,11 +734,17 @@ mod test_print_value {
         let mut writer = MockValueWriter::new();
         writer
             .expect_write_null()
-            .with(eq("foo"))
+            .with(eq(path(".foo")))
             .times(1)
             .returning(|_| Ok(()));
 
-        print_value("foo", Value::Null, &mut writer).unwrap();
+        print_value(
+            &mut path(".foo"),
+            Node::Null,
+            &mut writer,
+            &PrintOptions::default(),
+        )
+        .unwrap();
     }
 
     #[test]
@@ -196,37 +752,37 @@ mod test_print_value {
         let mut writer = MockValueWriter::new();
         writer
             .expect_write_string()
-            .with(eq(r#"."first name""#), eq("John"))
+            .with(eq(path(r#"."first name""#)), eq("John"))
             .times(1)
             .returning(|_, _| Ok(()));
         writer
             .expect_write_string()
-            .with(eq(r#"."last name""#), eq("Doe"))
+            .with(eq(path(r#"."last name""#)), eq("Doe"))
             .times(1)
             .returning(|_, _| Ok(()));
         writer
             .expect_write_number()
-            .with(eq(".age"), eq(Number::from(43)))
+            .with(eq(path(".age")), eq(Number::UInt(43)))
             .times(1)
             .returning(|_, _| Ok(()));
         writer
             .expect_write_string()
-            .with(eq(".address.street"), eq("10 Downing Street"))
+            .with(eq(path(".address.street")), eq("10 Downing Street"))
             .times(1)
             .returning(|_, _| Ok(()));
         writer
             .expect_write_string()
-            .with(eq(".address.city"), eq("London"))
+            .with(eq(path(".address.city")), eq("London"))
             .times(1)
             .returning(|_, _| Ok(()));
         writer
             .expect_write_string()
-            .with(eq(".phones[0]"), eq("+44 1234567"))
+            .with(eq(path(".phones[0]")), eq("+44 1234567"))
             .times(1)
             .returning(|_, _| Ok(()));
         writer
             .expect_write_string()
-            .with(eq(".phones[1]"), eq("+44 2345678"))
+            .with(eq(path(".phones[1]")), eq("+44 2345678"))
             .times(1)
             .returning(|_, _| Ok(()));
 
@@ -245,9 +801,15 @@ mod test_print_value {
             ]
         }"#;
 
-        let value: Value = serde_json::from_str(data).unwrap();
+        let value: Node = Node::from_str(data).unwrap();
 
-        print_value(".", value, &mut writer).unwrap();
+        print_value(
+            &mut Path::default(),
+            value,
+            &mut writer,
+            &PrintOptions::default(),
+        )
+        .unwrap();
     }
 
     #[test]
@@ -255,12 +817,12 @@ mod test_print_value {
         let mut writer = MockValueWriter::new();
         writer
             .expect_write_raw()
-            .with(eq(".address"), eq("{}"))
+            .with(eq(path(".address")), eq("{}"))
             .times(1)
             .returning(|_, _| Ok(()));
         writer
             .expect_write_raw()
-            .with(eq(".phones"), eq("[]"))
+            .with(eq(path(".phones")), eq("[]"))
             .times(1)
             .returning(|_, _| Ok(()));
 
@@ -270,54 +832,469 @@ mod test_print_value {
             "phones": []
         }"#;
 
-        let value: Value = serde_json::from_str(data).unwrap();
+        let value: Node = Node::from_str(data).unwrap();
 
-        print_value(".", value, &mut writer).unwrap();
+        print_value(
+            &mut Path::default(),
+            value,
+            &mut writer,
+            &PrintOptions::default(),
+        )
+        .unwrap();
 
         // let mut values = writer.buffer.lock().unwrap();
         // values.sort();
 
         // assert_eq!(*values, vec![".address => Raw({})", ".phones => Raw([])"]);
     }
-}
 
-#[cfg(test)]
-mod test_escape_path_element {
-    use super::escape_path_element;
+    #[test]
+    fn test_print_document() {
+        let mut writer = MockValueWriter::new();
+        writer
+            .expect_write_string()
+            .with(eq(path(r#"[1]."first name"[0]"#)), eq("John"))
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let value: Node = Node::from_str(r#"{"first name": ["John"]}"#).unwrap();
+
+        print_value(
+            &mut Path::new(Some(Segment::Index(1))),
+            value,
+            &mut writer,
+            &PrintOptions::default(),
+        )
+        .unwrap();
+    }
 
     #[test]
-    fn test_nothing_to_escape() {
-        assert_eq!(escape_path_element("foo".to_string()), "foo");
-        assert_eq!(
-            escape_path_element("key_with_underscores".to_string()),
-            "key_with_underscores"
+    fn test_print_deeply_nested_document() {
+        let depth = 100_000;
+
+        let mut expected = Path::default();
+        for _ in 0..depth {
+            expected.push(Segment::Index(0));
+        }
+
+        let mut writer = MockValueWriter::new();
+        writer
+            .expect_write_raw()
+            .with(eq(expected), eq("[]"))
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let mut value = Node::Array(Vec::new());
+        for _ in 0..depth {
+            value = Node::Array(vec![value]);
+        }
+
+        print_value(
+            &mut Path::default(),
+            value,
+            &mut writer,
+            &PrintOptions::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_print_with_sorted_keys() {
+        let value: Node =
+            Node::from_str(r#"{"item10": 0, "item2": 0, "b": {"z": 0, "a": 0}}"#).unwrap();
+
+        for (sort_keys, expected) in [
+            (None, [".item10", ".item2", ".b.z", ".b.a"]),
+            (
+                Some(CmdSortKeys::Lexicographic),
+                [".b.a", ".b.z", ".item10", ".item2"],
+            ),
+            (
+                Some(CmdSortKeys::Natural),
+                [".b.a", ".b.z", ".item2", ".item10"],
+            ),
+        ] {
+            let mut writer = MockValueWriter::new();
+            let mut seq = mockall::Sequence::new();
+            for p in expected {
+                writer
+                    .expect_write_number()
+                    .with(eq(path(p)), eq(Number::UInt(0)))
+                    .times(1)
+                    .in_sequence(&mut seq)
+                    .returning(|_, _| Ok(()));
+            }
+
+            print_value(
+                &mut Path::default(),
+                value.clone(),
+                &mut writer,
+                &PrintOptions {
+                    sort_keys,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_print_tagged_values() {
+        let mut value = Mapping::new();
+        value.insert(
+            Key::String("arn".to_string()),
+            Node::Tagged(
+                "!GetAtt".to_string(),
+                Box::new(Node::Array(vec![Node::String("Bucket".to_string())])),
+            ),
+        );
+        value.insert(
+            Key::String("data".to_string()),
+            Node::Binary(b"Hello".to_vec()),
         );
+
+        let mut writer = MockValueWriter::new();
+        let mut seq = mockall::Sequence::new();
+        writer
+            .expect_write_tag()
+            .with(eq(path(".arn")), eq("!GetAtt"))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _| Ok(()));
+        writer
+            .expect_write_string()
+            .with(eq(path(".arn[0]")), eq("Bucket"))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _| Ok(()));
+        writer
+            .expect_write_tag()
+            .with(eq(path(".data")), eq("!!binary"))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _| Ok(()));
+        writer
+            .expect_write_string()
+            .with(eq(path(".data")), eq("SGVsbG8="))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _| Ok(()));
+
+        print_value(
+            &mut Path::default(),
+            Node::Object(value),
+            &mut writer,
+            &PrintOptions::default(),
+        )
+        .unwrap();
     }
 
     #[test]
-    fn test_wrap_strings_with_non_alnum_chars_in_double_quotes() {
-        assert_eq!(
-            escape_path_element("Key with brackets [0]".to_string()),
-            "\"Key with brackets [0]\""
+    fn test_print_merged_values() {
+        let mut merged = Mapping::new();
+        merged.insert(Key::String("a".to_string()), Node::Null);
+        merged.insert(Key::String("b".to_string()), Node::Null);
+
+        let mut value = Mapping::new();
+        value.insert(
+            Key::String("x".to_string()),
+            Node::Merged(Box::new(Node::Object(merged))),
         );
-        assert_eq!(
-            escape_path_element("key-with-dashes".to_string()),
-            "\"key-with-dashes\""
+        value.insert(Key::String("y".to_string()), Node::Null);
+
+        let mut writer = MockValueWriter::new();
+        let mut seq = mockall::Sequence::new();
+        writer
+            .expect_set_merged()
+            .with(eq(true))
+            .times(1)
+            .in_sequence(&mut seq)
+            .return_const(());
+        for p in [".x.a", ".x.b"] {
+            writer
+                .expect_write_null()
+                .with(eq(path(p)))
+                .times(1)
+                .in_sequence(&mut seq)
+                .returning(|_| Ok(()));
+        }
+        writer
+            .expect_set_merged()
+            .with(eq(false))
+            .times(1)
+            .in_sequence(&mut seq)
+            .return_const(());
+        writer
+            .expect_write_null()
+            .with(eq(path(".y")))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok(()));
+
+        print_value(
+            &mut Path::default(),
+            Node::Object(value),
+            &mut writer,
+            &PrintOptions::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_print_selected_values() {
+        let value: Node = Node::from_str(
+            r#"{
+                "metadata": {"name": "web", "password": "secret"},
+                "spec": {"containers": [{"name": "a", "image": "x"}, {"name": "b", "image": {}}]}
+            }"#,
+        )
+        .unwrap();
+
+        let mut writer = MockValueWriter::new();
+        let mut seq = mockall::Sequence::new();
+        writer
+            .expect_write_string()
+            .with(eq(path("[1].metadata.password")), eq("secret"))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _| Ok(()));
+        writer
+            .expect_write_string()
+            .with(eq(path("[1].spec.containers[0].image")), eq("x"))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _| Ok(()));
+        writer
+            .expect_write_raw()
+            .with(eq(path("[1].spec.containers[1].image")), eq("{}"))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _| Ok(()));
+
+        let selector = Selector::new(
+            &[
+                ".spec.containers[*].image".to_string(),
+                "..password".to_string(),
+            ],
+            &[],
+        )
+        .unwrap();
+        print_value(
+            &mut Path::new(Some(Segment::Index(1))),
+            value,
+            &mut writer,
+            &PrintOptions {
+                selector,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_print_excluded_values() {
+        let value: Node = Node::from_str(
+            r#"{
+                "metadata": {"name": "web", "managedFields": [{"manager": "kubectl"}]},
+                "spec": {"replicas": 2},
+                "status": {"replicas": 2, "conditions": [{}, {"type": "Available"}]}
+            }"#,
+        )
+        .unwrap();
+
+        for mark_excluded in [false, true] {
+            let mut writer = MockValueWriter::new();
+            let mut seq = mockall::Sequence::new();
+            writer
+                .expect_write_string()
+                .with(eq(path(".metadata.name")), eq("web"))
+                .times(1)
+                .in_sequence(&mut seq)
+                .returning(|_, _| Ok(()));
+            if mark_excluded {
+                writer
+                    .expect_write_excluded()
+                    .with(eq(path(".metadata.managedFields")), eq(1))
+                    .times(1)
+                    .in_sequence(&mut seq)
+                    .returning(|_, _| Ok(()));
+            }
+            writer
+                .expect_write_number()
+                .with(eq(path(".spec.replicas")), eq(Number::UInt(2)))
+                .times(1)
+                .in_sequence(&mut seq)
+                .returning(|_, _| Ok(()));
+            if mark_excluded {
+                writer
+                    .expect_write_excluded()
+                    .with(eq(path(".status")), eq(3))
+                    .times(1)
+                    .in_sequence(&mut seq)
+                    .returning(|_, _| Ok(()));
+            }
+
+            let selector = Selector::new(
+                &[],
+                &[".metadata.managedFields".to_string(), ".status".to_string()],
+            )
+            .unwrap();
+            print_value(
+                &mut Path::default(),
+                value.clone(),
+                &mut writer,
+                &PrintOptions {
+                    selector,
+                    mark_excluded,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_print_collapsed_values() {
+        let value: Node =
+            Node::from_str(r#"{"a": {"b": {"c": 1}, "d": []}, "e": [1, 2], "f": 3}"#).unwrap();
+
+        let mut writer = MockValueWriter::new();
+        let mut seq = mockall::Sequence::new();
+        let collapsed = [
+            (".a.b", Node::from_str(r#"{"c": 1}"#).unwrap()),
+            (".a.d", Node::Array(vec![])),
+        ];
+        // `.e` isn't selected, and isn't written at all.
+        for (p, v) in collapsed {
+            writer
+                .expect_write_collapsed()
+                .with(eq(path(p)), eq(v))
+                .times(1)
+                .in_sequence(&mut seq)
+                .returning(|_, _| Ok(()));
+        }
+        writer
+            .expect_write_number()
+            .with(eq(path(".f")), eq(Number::UInt(3)))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _| Ok(()));
+
+        print_value(
+            &mut Path::default(),
+            value,
+            &mut writer,
+            &PrintOptions {
+                max_depth: Some(2),
+                selector: Selector::new(&[".a".to_string(), ".f".to_string()], &[]).unwrap(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_print_collapsed_deeply_nested_document() {
+        let depth = 100_000;
+        let value: Node = format!("{}{}", "[".repeat(depth), "]".repeat(depth))
+            .parse()
+            .unwrap();
+
+        // The mock writer would print the arguments of the calls, which recurses as deep as them.
+        let mut buffer = Vec::new();
+        let mut writer = get_writer(
+            &mut buffer,
+            CmdColor::Never,
+            false,
+            &JqDialect,
+            None,
+            WriterOptions::default(),
         );
+        print_value(
+            &mut Path::default(),
+            value,
+            &mut *writer,
+            &PrintOptions {
+                max_depth: Some(1),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        drop(writer);
+
+        let expected = format!(
+            ".[0] => {}{}\n",
+            "[".repeat(depth - 1),
+            "]".repeat(depth - 1)
+        );
+        assert_eq!(String::from_utf8(buffer).unwrap(), expected);
     }
 
     #[test]
-    /// Keys composed of only digits need to be wrapped in quotes
-    fn test_wrap_strings_with_only_digits_in_double_quotes() {
-        assert_eq!(escape_path_element("123".to_string()), "\"123\"");
-        assert_eq!(escape_path_element("abc123".to_string()), "abc123");
+    fn test_collapsed_value() {
+        let value: Node = r#"{"a": [1, 2.50, "x\n"], "b": {}}"#.parse().unwrap();
+        assert_eq!(
+            collapsed_value(&value, false, false),
+            r#"{"a":[1,2.50,"x\n"],"b":{}}"#
+        );
+        assert_eq!(
+            collapsed_value(&value, false, true),
+            r#"{"a":[1,2.5,"x\n"],"b":{}}"#
+        );
+        assert_eq!(collapsed_value(&value, true, false), "{2 keys}");
+
+        for (json, expected) in [
+            ("{}", "{}"),
+            (r#"{"a": 1}"#, "{1 key}"),
+            ("[]", "[]"),
+            ("[[]]", "[1 item]"),
+            ("[1, 2, 3]", "[3 items]"),
+        ] {
+            let value: Node = json.parse().unwrap();
+            assert_eq!(collapsed_value(&value, true, false), expected);
+        }
     }
 
     #[test]
-    fn test_escape_double_quotes() {
+    fn test_collapsed_yaml_values() {
+        let mut object = Mapping::new();
+        object.insert(
+            Key::Other("1".to_string()),
+            Node::Array(vec![
+                Node::Number(Number::Float(f64::NAN)),
+                Node::Number(Number::Float(f64::NEG_INFINITY)),
+                Node::Tagged("!Ref".to_string(), Box::new(Node::Bool(true))),
+                Node::Binary(b"Hello".to_vec()),
+                Node::DateTime("1979-05-27".parse().unwrap()),
+                Node::Number(Number::Literal(
+                    "0x1f".to_string(),
+                    Box::new(Number::UInt(31)),
+                )),
+                Node::Number(Number::Literal(
+                    "+.5".to_string(),
+                    Box::new(Number::Float(0.5)),
+                )),
+            ]),
+        );
+
+        // The result is valid JSON.
+        let json = collapsed_value(&Node::Object(object), false, false);
         assert_eq!(
-            escape_path_element("Mathieu \"Uncle Matt\" Lemay".to_string()),
-            r#""Mathieu \"Uncle Matt\" Lemay""#
+            json,
+            r#"{"1":["nan","-inf",true,"SGVsbG8=","1979-05-27",31,0.5]}"#
         );
+        assert!(json.parse::<Node>().is_ok());
+    }
+
+    #[test]
+    fn test_collapsed_deeply_nested_value() {
+        let depth = 100_000;
+        let value: Node = format!("{}{}", "[".repeat(depth), "]".repeat(depth))
+            .parse()
+            .unwrap();
+
+        let expected = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert_eq!(collapsed_value(&value, false, false), expected);
+        drop_value(value);
     }
 }
@@ -1,108 +1,245 @@
 use std::io::Write;
 use std::str;
 
-use serde_json::Number;
-use termcolor::{Buffer, BufferWriter, Color, ColorChoice, ColorSpec, WriteColor};
-
-use super::{escape_str_value, ValueWriter};
+use termcolor::{Buffer, BufferWriter, ColorChoice, ColorSpec};
+use toml::value::Datetime;
+
+use super::{
+    collapsed_value, write_line_parts, ValueWriter, WriterOptions, DATETIME_VALUE_COLOR_SPEC,
+    KEY_COLOR_SPEC, NULL_VALUE_COLOR_SPEC, RAW_VALUE_COLOR_SPEC, STRING_VALUE_COLOR_SPEC,
+    TAG_COLOR_SPEC,
+};
+use crate::document::{Node, Number};
 use crate::error::Result;
-
-lazy_static! {
-    static ref KEY_COLOR_SPEC: ColorSpec = ColorSpec::new().set_fg(Some(Color::Blue)).clone();
-    static ref STRING_VALUE_COLOR_SPEC: ColorSpec =
-        ColorSpec::new().set_fg(Some(Color::Green)).clone();
-    static ref NULL_VALUE_COLOR_SPEC: ColorSpec = ColorSpec::new()
-        .set_fg(Some(Color::Black))
-        .set_bold(true)
-        .clone();
-    static ref RAW_VALUE_COLOR_SPEC: ColorSpec = ColorSpec::new().set_bold(true).clone();
-}
+use crate::path::{to_json_string, Path, PathDialect};
 
 pub(super) struct ConsoleWriter<'a> {
     writer: &'a mut dyn Write,
     buffer: Buffer,
+    dialect: &'static dyn PathDialect,
+    /// Tag of the next value, with its path.
+    tag: Option<(Path, String)>,
+    /// Whether the values written come from a YAML merge key.
+    merged: bool,
+    /// Name of the file of the values written, printed before each line.
+    file: Option<String>,
+    options: WriterOptions,
+    /// Number of values selected by the filter.
+    matches: usize,
 }
 
 impl<'a> ConsoleWriter<'a> {
-    pub(super) fn new(writer: &'a mut dyn Write, color_choice: ColorChoice) -> Self {
+    pub(super) fn new(
+        writer: &'a mut dyn Write,
+        color_choice: ColorChoice,
+        dialect: &'static dyn PathDialect,
+        options: WriterOptions,
+    ) -> Self {
         let buffer = BufferWriter::stdout(color_choice).buffer();
-        Self { writer, buffer }
+        Self {
+            writer,
+            buffer,
+            dialect,
+            tag: None,
+            merged: false,
+            file: None,
+            options,
+            matches: 0,
+        }
+    }
+
+    /// Writes the pending tag on a line of its own, unless it is the tag of the value at `path`,
+    /// in which case it is returned.
+    fn take_tag(&mut self, path: &Path) -> Result<Option<String>> {
+        match self.tag.take() {
+            Some((tag_path, tag)) if tag_path == *path => Ok(Some(tag)),
+            Some((tag_path, tag)) => {
+                self.write_line(&tag_path, None, &tag, Some(&TAG_COLOR_SPEC))?;
+                Ok(None)
+            }
+            None => Ok(None),
+        }
     }
 
     fn write_value(
         &mut self,
-        path: &str,
+        path: &Path,
         value: &str,
         value_color_spec: Option<&ColorSpec>,
     ) -> Result<()> {
-        self.buffer
-            .set_color(ColorSpec::new().set_fg(Some(Color::Blue)))?;
-        write!(&mut self.buffer, "{}", path)?;
-        self.buffer.reset()?;
+        let tag = self.take_tag(path)?;
+        if self.write_line(path, tag.as_deref(), value, value_color_spec)? {
+            self.matches += 1;
+        }
 
-        write!(&mut self.buffer, " => ")?;
+        Ok(())
+    }
 
-        if let Some(spec) = value_color_spec {
-            self.buffer.set_color(spec)?;
-            writeln!(&mut self.buffer, "{}", value)?;
-            self.buffer.reset()?;
-        } else {
-            writeln!(&mut self.buffer, "{}", value)?;
+    fn write_line(
+        &mut self,
+        path: &Path,
+        tag: Option<&str>,
+        value: &str,
+        value_color_spec: Option<&ColorSpec>,
+    ) -> Result<bool> {
+        if self.options.is_done(self.matches) {
+            return Ok(false);
         }
 
+        let path = path.display(self.dialect).to_string();
+        let mut parts = vec![(path.as_str(), Some(&*KEY_COLOR_SPEC)), (" => ", None)];
+        if let Some(tag) = tag {
+            parts.extend([(tag, Some(&*TAG_COLOR_SPEC)), (" ", None)]);
+        }
+        let value_index = parts.len();
+        parts.push((value, value_color_spec));
+        if self.merged {
+            parts.extend([(" ", None), ("(merged)", Some(&*TAG_COLOR_SPEC))]);
+        }
+
+        let selected = write_line_parts(
+            &mut self.buffer,
+            &self.options,
+            self.file.as_deref(),
+            &parts,
+            0,
+            value_index,
+        )?;
+
         let value = str::from_utf8(self.buffer.as_slice())?;
         write!(self.writer, "{}", value)?;
         self.buffer.clear();
 
-        Ok(())
+        Ok(selected)
     }
 }
 
 impl ValueWriter for ConsoleWriter<'_> {
-    fn write_string(&mut self, path: &str, value: &str) -> Result<()> {
-        self.write_value(
-            path,
-            &escape_str_value(value),
-            Some(&STRING_VALUE_COLOR_SPEC),
-        )
+    fn write_string(&mut self, path: &Path, value: &str) -> Result<()> {
+        let value = if self.options.raw_strings {
+            value.to_string()
+        } else {
+            to_json_string(value)
+        };
+
+        self.write_value(path, &value, Some(&STRING_VALUE_COLOR_SPEC))
     }
 
-    fn write_number(&mut self, path: &str, value: &Number) -> Result<()> {
+    fn write_number(&mut self, path: &Path, value: &Number) -> Result<()> {
+        let value = if self.options.normalize_numbers {
+            value.value()
+        } else {
+            value
+        };
         self.write_value(path, &value.to_string(), None)
     }
 
-    fn write_bool(&mut self, path: &str, value: bool) -> Result<()> {
+    fn write_bool(&mut self, path: &Path, value: bool) -> Result<()> {
         self.write_value(path, &value.to_string(), None)
     }
 
-    fn write_null(&mut self, path: &str) -> Result<()> {
+    fn write_null(&mut self, path: &Path) -> Result<()> {
         self.write_value(path, "null", Some(&NULL_VALUE_COLOR_SPEC))
     }
 
-    fn write_raw(&mut self, path: &str, value: &str) -> Result<()> {
+    fn write_datetime(&mut self, path: &Path, value: &Datetime) -> Result<()> {
+        self.write_value(path, &value.to_string(), Some(&DATETIME_VALUE_COLOR_SPEC))
+    }
+
+    fn write_raw(&mut self, path: &Path, value: &str) -> Result<()> {
         self.write_value(path, value, Some(&RAW_VALUE_COLOR_SPEC))
     }
+
+    fn write_collapsed(&mut self, path: &Path, value: &Node) -> Result<()> {
+        let value = collapsed_value(
+            value,
+            self.options.summarize,
+            self.options.normalize_numbers,
+        );
+        self.write_value(path, &value, Some(&RAW_VALUE_COLOR_SPEC))
+    }
+
+    /// Markers are written like values, but aren't counted as matches.
+    fn write_excluded(&mut self, path: &Path, leaves: usize) -> Result<()> {
+        let marker = match leaves {
+            1 => "<excluded, 1 leaf>".to_string(),
+            n => format!("<excluded, {} leaves>", n),
+        };
+        let tag = self.take_tag(path)?;
+        self.write_line(path, tag.as_deref(), &marker, Some(&TAG_COLOR_SPEC))?;
+
+        Ok(())
+    }
+
+    fn write_tag(&mut self, path: &Path, tag: &str) -> Result<()> {
+        // The previous tag belongs to a container that holds this value.
+        self.take_tag(path)?;
+        self.tag = Some((path.clone(), tag.to_string()));
+
+        Ok(())
+    }
+
+    fn write_alias(&mut self, path: &Path, name: &str, anchor: &Path) -> Result<()> {
+        let value = format!("*{} (see {})", name, anchor.display(self.dialect));
+        self.write_value(path, &value, Some(&TAG_COLOR_SPEC))
+    }
+
+    fn set_merged(&mut self, merged: bool) {
+        self.merged = merged;
+    }
+
+    fn set_file(&mut self, file: Option<String>) {
+        self.file = file;
+    }
+
+    fn matches(&self) -> usize {
+        self.matches
+    }
+
+    fn is_done(&self) -> bool {
+        self.options.is_done(self.matches)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        // The tag of a container whose values were all left out, at the end of the document.
+        if let Some((path, tag)) = self.tag.take() {
+            self.write_line(&path, None, &tag, Some(&TAG_COLOR_SPEC))?;
+        }
+        Ok(self.writer.flush()?)
+    }
 }
 
 #[cfg(test)]
 mod test_console_writer {
-    use serde_json::Number;
+    use crate::document::Number;
     use termcolor::ColorChoice;
 
-    use super::{ConsoleWriter, ValueWriter};
+    use super::{ConsoleWriter, ValueWriter, WriterOptions};
+    use crate::filter::Filter;
+    use crate::path::{JqDialect, JsonPointerDialect, Path, Segment};
 
     static FORMAT_RESET: &str = "\u{1b}[0m";
     static FORMAT_BOLD: &str = "\u{1b}[1m";
+    static COLOR_RED: &str = "\u{1b}[31m";
     static COLOR_BLACK: &str = "\u{1b}[30m";
     static COLOR_GREEN: &str = "\u{1b}[32m";
     static COLOR_BLUE: &str = "\u{1b}[34m";
+    static COLOR_YELLOW: &str = "\u{1b}[33m";
+    static COLOR_MAGENTA: &str = "\u{1b}[35m";
 
     #[test]
     fn test_write_string_with_color() {
         let mut buffer = Vec::new();
-        let mut writer = ConsoleWriter::new(&mut buffer, ColorChoice::Always);
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Always,
+            &JqDialect,
+            WriterOptions::default(),
+        );
 
-        writer.write_string(".", "Some String").unwrap();
+        writer
+            .write_string(&Path::default(), "Some String")
+            .unwrap();
 
         let actual = std::str::from_utf8(buffer.as_slice()).unwrap();
         let expected = format!(
@@ -115,20 +252,74 @@ mod test_console_writer {
     #[test]
     fn test_write_string_no_color() {
         let mut buffer = Vec::new();
-        let mut writer = ConsoleWriter::new(&mut buffer, ColorChoice::Never);
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Never,
+            &JqDialect,
+            WriterOptions::default(),
+        );
 
-        writer.write_string(".", "Some String").unwrap();
+        writer
+            .write_string(&Path::default(), "Some String")
+            .unwrap();
 
         let value = std::str::from_utf8(buffer.as_slice()).unwrap();
         assert_eq!(". => \"Some String\"\n", value);
     }
 
+    #[test]
+    fn test_write_string_escapes() {
+        let mut buffer = Vec::new();
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Never,
+            &JqDialect,
+            WriterOptions::default(),
+        );
+
+        writer
+            .write_string(&Path::default(), "a \"b\"\t\\c\r\n\u{1b}[31md\u{7f}\u{9b}")
+            .unwrap();
+
+        let value = std::str::from_utf8(buffer.as_slice()).unwrap();
+        assert_eq!(
+            ". => \"a \\\"b\\\"\\t\\\\c\\r\\n\\u001b[31md\\u007f\\u009b\"\n",
+            value
+        );
+    }
+
+    #[test]
+    fn test_write_raw_strings() {
+        let mut buffer = Vec::new();
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Never,
+            &JqDialect,
+            WriterOptions {
+                raw_strings: true,
+                ..Default::default()
+            },
+        );
+
+        writer.write_string(&Path::default(), "a \"b\"\tc").unwrap();
+
+        let value = std::str::from_utf8(buffer.as_slice()).unwrap();
+        assert_eq!(". => a \"b\"\tc\n", value);
+    }
+
     #[test]
     fn test_write_number_with_color() {
         let mut buffer = Vec::new();
-        let mut writer = ConsoleWriter::new(&mut buffer, ColorChoice::Always);
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Always,
+            &JqDialect,
+            WriterOptions::default(),
+        );
 
-        writer.write_number(".", &Number::from(420)).unwrap();
+        writer
+            .write_number(&Path::default(), &Number::UInt(420))
+            .unwrap();
 
         let actual = std::str::from_utf8(buffer.as_slice()).unwrap();
         let expected = format!("{}{}.{} => 420\n", FORMAT_RESET, COLOR_BLUE, FORMAT_RESET);
@@ -138,20 +329,73 @@ mod test_console_writer {
     #[test]
     fn test_write_number_no_color() {
         let mut buffer = Vec::new();
-        let mut writer = ConsoleWriter::new(&mut buffer, ColorChoice::Never);
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Never,
+            &JqDialect,
+            WriterOptions::default(),
+        );
 
-        writer.write_number(".", &Number::from(420)).unwrap();
+        writer
+            .write_number(&Path::default(), &Number::UInt(420))
+            .unwrap();
 
         let value = std::str::from_utf8(buffer.as_slice()).unwrap();
         assert_eq!(". => 420\n", value);
     }
 
+    #[test]
+    fn test_write_special_floats() {
+        let mut buffer = Vec::new();
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Never,
+            &JqDialect,
+            WriterOptions::default(),
+        );
+
+        for x in [f64::INFINITY, f64::NEG_INFINITY, f64::NAN, -0.0] {
+            writer
+                .write_number(&Path::default(), &Number::Float(x))
+                .unwrap();
+        }
+
+        let value = std::str::from_utf8(buffer.as_slice()).unwrap();
+        assert_eq!(". => inf\n. => -inf\n. => nan\n. => -0.0\n", value);
+    }
+
+    #[test]
+    fn test_write_number_literals() {
+        let n: Number = "1.50".parse().unwrap();
+
+        for (normalize_numbers, expected) in [(false, ". => 1.50\n"), (true, ". => 1.5\n")] {
+            let mut buffer = Vec::new();
+            let mut writer = ConsoleWriter::new(
+                &mut buffer,
+                ColorChoice::Never,
+                &JqDialect,
+                WriterOptions {
+                    normalize_numbers,
+                    ..Default::default()
+                },
+            );
+            writer.write_number(&Path::default(), &n).unwrap();
+
+            assert_eq!(std::str::from_utf8(buffer.as_slice()).unwrap(), expected);
+        }
+    }
+
     #[test]
     fn test_write_bool_with_color() {
         let mut buffer = Vec::new();
-        let mut writer = ConsoleWriter::new(&mut buffer, ColorChoice::Always);
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Always,
+            &JqDialect,
+            WriterOptions::default(),
+        );
 
-        writer.write_bool(".", true).unwrap();
+        writer.write_bool(&Path::default(), true).unwrap();
 
         let actual = std::str::from_utf8(buffer.as_slice()).unwrap();
         let expected = format!("{}{}.{} => true\n", FORMAT_RESET, COLOR_BLUE, FORMAT_RESET);
@@ -161,9 +405,14 @@ mod test_console_writer {
     #[test]
     fn test_write_bool_no_color() {
         let mut buffer = Vec::new();
-        let mut writer = ConsoleWriter::new(&mut buffer, ColorChoice::Never);
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Never,
+            &JqDialect,
+            WriterOptions::default(),
+        );
 
-        writer.write_bool(".", true).unwrap();
+        writer.write_bool(&Path::default(), true).unwrap();
 
         let value = std::str::from_utf8(buffer.as_slice()).unwrap();
         assert_eq!(". => true\n", value);
@@ -172,9 +421,14 @@ mod test_console_writer {
     #[test]
     fn test_write_null_with_color() {
         let mut buffer = Vec::new();
-        let mut writer = ConsoleWriter::new(&mut buffer, ColorChoice::Always);
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Always,
+            &JqDialect,
+            WriterOptions::default(),
+        );
 
-        writer.write_null(".").unwrap();
+        writer.write_null(&Path::default()).unwrap();
 
         let actual = std::str::from_utf8(buffer.as_slice()).unwrap();
         let expected = format!(
@@ -193,20 +447,68 @@ mod test_console_writer {
     #[test]
     fn test_write_null_no_color() {
         let mut buffer = Vec::new();
-        let mut writer = ConsoleWriter::new(&mut buffer, ColorChoice::Never);
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Never,
+            &JqDialect,
+            WriterOptions::default(),
+        );
 
-        writer.write_null(".").unwrap();
+        writer.write_null(&Path::default()).unwrap();
 
         let value = std::str::from_utf8(buffer.as_slice()).unwrap();
         assert_eq!(". => null\n", value);
     }
 
+    #[test]
+    fn test_write_datetime_with_color() {
+        let mut buffer = Vec::new();
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Always,
+            &JqDialect,
+            WriterOptions::default(),
+        );
+
+        let datetime = "1979-05-27T07:32:00-08:00".parse().unwrap();
+        writer.write_datetime(&Path::default(), &datetime).unwrap();
+
+        let actual = std::str::from_utf8(buffer.as_slice()).unwrap();
+        let expected = format!(
+            "{}{}.{} => {}{}1979-05-27T07:32:00-08:00\n{}",
+            FORMAT_RESET, COLOR_BLUE, FORMAT_RESET, FORMAT_RESET, COLOR_MAGENTA, FORMAT_RESET
+        );
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_write_datetime_no_color() {
+        let mut buffer = Vec::new();
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Never,
+            &JqDialect,
+            WriterOptions::default(),
+        );
+
+        let datetime = "07:32:00".parse().unwrap();
+        writer.write_datetime(&Path::default(), &datetime).unwrap();
+
+        let value = std::str::from_utf8(buffer.as_slice()).unwrap();
+        assert_eq!(". => 07:32:00\n", value);
+    }
+
     #[test]
     fn test_write_raw_with_color() {
         let mut buffer = Vec::new();
-        let mut writer = ConsoleWriter::new(&mut buffer, ColorChoice::Always);
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Always,
+            &JqDialect,
+            WriterOptions::default(),
+        );
 
-        writer.write_raw(".", "Some String").unwrap();
+        writer.write_raw(&Path::default(), "Some String").unwrap();
 
         let actual = std::str::from_utf8(buffer.as_slice()).unwrap();
         let expected = format!(
@@ -219,11 +521,288 @@ mod test_console_writer {
     #[test]
     fn test_write_raw_no_color() {
         let mut buffer = Vec::new();
-        let mut writer = ConsoleWriter::new(&mut buffer, ColorChoice::Never);
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Never,
+            &JqDialect,
+            WriterOptions::default(),
+        );
 
-        writer.write_raw(".", "Some String").unwrap();
+        writer.write_raw(&Path::default(), "Some String").unwrap();
 
         let value = std::str::from_utf8(buffer.as_slice()).unwrap();
         assert_eq!(". => Some String\n", value);
     }
+
+    #[test]
+    fn test_write_with_path_dialect() {
+        let mut buffer = Vec::new();
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Never,
+            &JsonPointerDialect,
+            WriterOptions::default(),
+        );
+
+        let path = Path::from(vec![
+            Segment::Key("first name".to_string()),
+            Segment::Index(0),
+        ]);
+        writer.write_null(&path).unwrap();
+
+        let value = std::str::from_utf8(buffer.as_slice()).unwrap();
+        assert_eq!("/first name/0 => null\n", value);
+    }
+
+    #[test]
+    fn test_write_tag_with_color() {
+        let mut buffer = Vec::new();
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Always,
+            &JqDialect,
+            WriterOptions::default(),
+        );
+
+        writer.write_tag(&Path::default(), "!Ref").unwrap();
+        writer.write_string(&Path::default(), "Bucket").unwrap();
+
+        let actual = std::str::from_utf8(buffer.as_slice()).unwrap();
+        let expected = format!(
+            "{}{}.{} => {}{}!Ref{} {}{}\"Bucket\"\n{}",
+            FORMAT_RESET,
+            COLOR_BLUE,
+            FORMAT_RESET,
+            FORMAT_RESET,
+            COLOR_YELLOW,
+            FORMAT_RESET,
+            FORMAT_RESET,
+            COLOR_GREEN,
+            FORMAT_RESET
+        );
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_pending_tag_is_written_on_flush() {
+        let mut buffer = Vec::new();
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Never,
+            &JqDialect,
+            WriterOptions::default(),
+        );
+
+        let path = Path::from(vec![Segment::Key("a".to_string())]);
+        writer.write_tag(&path, "!Foo").unwrap();
+        writer.flush().unwrap();
+
+        let value = std::str::from_utf8(buffer.as_slice()).unwrap();
+        assert_eq!(value, ".a => !Foo\n");
+    }
+
+    #[test]
+    fn test_write_tags_no_color() {
+        let mut buffer = Vec::new();
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Never,
+            &JqDialect,
+            WriterOptions::default(),
+        );
+
+        let path = |segments: &[Segment]| Path::from(segments.to_vec());
+        let arn = Segment::Key("arn".to_string());
+
+        writer.write_tag(&path(&[]), "!If").unwrap();
+        writer
+            .write_tag(&path(std::slice::from_ref(&arn)), "!GetAtt")
+            .unwrap();
+        writer
+            .write_string(&path(&[arn.clone(), Segment::Index(0)]), "Bucket")
+            .unwrap();
+        writer
+            .write_string(&path(&[arn, Segment::Index(1)]), "Arn")
+            .unwrap();
+        writer
+            .write_number(
+                &path(&[Segment::Key("port".to_string())]),
+                &Number::UInt(80),
+            )
+            .unwrap();
+
+        let value = std::str::from_utf8(buffer.as_slice()).unwrap();
+        assert_eq!(
+            value,
+            concat!(
+                ". => !If\n",
+                ".arn => !GetAtt\n",
+                ".arn[0] => \"Bucket\"\n",
+                ".arn[1] => \"Arn\"\n",
+                ".port => 80\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_write_alias_no_color() {
+        let mut buffer = Vec::new();
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Never,
+            &JsonPointerDialect,
+            WriterOptions::default(),
+        );
+
+        let anchor = Path::from(vec![Segment::Key("defaults".to_string())]);
+        let path = Path::from(vec![Segment::Key("jobs".to_string()), Segment::Index(0)]);
+        writer.write_alias(&path, "defaults", &anchor).unwrap();
+
+        let value = std::str::from_utf8(buffer.as_slice()).unwrap();
+        assert_eq!("/jobs/0 => *defaults (see /defaults)\n", value);
+    }
+
+    #[test]
+    fn test_write_merged_no_color() {
+        let mut buffer = Vec::new();
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Never,
+            &JqDialect,
+            WriterOptions::default(),
+        );
+
+        let path = |key: &str| Path::from(vec![Segment::Key(key.to_string())]);
+        writer.set_merged(true);
+        writer.write_string(&path("image"), "alpine").unwrap();
+        writer.set_merged(false);
+        writer.write_string(&path("script"), "make").unwrap();
+
+        let value = std::str::from_utf8(buffer.as_slice()).unwrap();
+        assert_eq!(
+            value,
+            ".image => \"alpine\" (merged)\n.script => \"make\"\n"
+        );
+    }
+
+    #[test]
+    fn test_write_excluded_no_color() {
+        let mut buffer = Vec::new();
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Never,
+            &JqDialect,
+            WriterOptions::default(),
+        );
+
+        let path = |key: &str| Path::from(vec![Segment::Key(key.to_string())]);
+        writer.write_excluded(&path("status"), 143).unwrap();
+        writer.write_excluded(&path("data"), 1).unwrap();
+        assert_eq!(writer.matches(), 0);
+
+        let value = std::str::from_utf8(buffer.as_slice()).unwrap();
+        assert_eq!(
+            value,
+            ".status => <excluded, 143 leaves>\n.data => <excluded, 1 leaf>\n"
+        );
+    }
+
+    #[test]
+    fn test_write_filtered_lines_with_color() {
+        let mut buffer = Vec::new();
+        let filter = Filter::new(&[], &["^\\.spec".to_string()], &[], false, false).unwrap();
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Always,
+            &JqDialect,
+            WriterOptions {
+                filter,
+                ..Default::default()
+            },
+        );
+
+        let path = |key: &str| Path::from(vec![Segment::Key(key.to_string())]);
+        writer.write_string(&path("metadata"), "spec").unwrap();
+        writer.write_string(&path("spec"), "web").unwrap();
+
+        let actual = std::str::from_utf8(buffer.as_slice()).unwrap();
+        let expected = format!(
+            "{}{}{}.spec{} => {}{}\"web\"\n{}",
+            FORMAT_RESET,
+            FORMAT_BOLD,
+            COLOR_RED,
+            FORMAT_RESET,
+            FORMAT_RESET,
+            COLOR_GREEN,
+            FORMAT_RESET
+        );
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_write_filtered_lines_no_color() {
+        let mut buffer = Vec::new();
+        let filter = Filter::new(&[], &[], &["^\"a".to_string()], true, false).unwrap();
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Never,
+            &JqDialect,
+            WriterOptions {
+                filter,
+                ..Default::default()
+            },
+        );
+
+        let path = |key: &str| Path::from(vec![Segment::Key(key.to_string())]);
+        writer.write_string(&path("image"), "Alpine").unwrap();
+        writer.write_string(&path("arch"), "x86").unwrap();
+
+        let value = std::str::from_utf8(buffer.as_slice()).unwrap();
+        assert_eq!(value, ".image => \"Alpine\"\n");
+    }
+
+    #[test]
+    fn test_count_and_max_count() {
+        let mut buffer = Vec::new();
+        let filter = Filter::new(&["a".to_string()], &[], &[], false, false).unwrap();
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Never,
+            &JqDialect,
+            WriterOptions {
+                filter,
+                max_count: Some(2),
+                ..Default::default()
+            },
+        );
+
+        for value in ["a", "b", "aa", "aaa"] {
+            writer.write_string(&Path::default(), value).unwrap();
+        }
+        assert!(writer.is_done());
+        assert_eq!(writer.matches(), 2);
+
+        let value = std::str::from_utf8(buffer.as_slice()).unwrap();
+        assert_eq!(value, ". => \"a\"\n. => \"aa\"\n");
+    }
+
+    #[test]
+    fn test_quiet() {
+        let mut buffer = Vec::new();
+        let mut writer = ConsoleWriter::new(
+            &mut buffer,
+            ColorChoice::Never,
+            &JqDialect,
+            WriterOptions {
+                quiet: true,
+                ..Default::default()
+            },
+        );
+
+        writer.write_string(&Path::default(), "a").unwrap();
+        writer.write_null(&Path::default()).unwrap();
+        assert_eq!(writer.matches(), 2);
+
+        assert!(buffer.is_empty());
+    }
 }
@@ -0,0 +1,396 @@
+use std::io::Write;
+use std::str;
+
+use termcolor::{Buffer, BufferWriter, ColorChoice, ColorSpec};
+use toml::value::Datetime;
+
+use super::{
+    collapsed_value, write_line_parts, ValueWriter, WriterOptions, DATETIME_VALUE_COLOR_SPEC,
+    KEY_COLOR_SPEC, NULL_VALUE_COLOR_SPEC, RAW_VALUE_COLOR_SPEC, STRING_VALUE_COLOR_SPEC,
+};
+use crate::document::{Node, Number};
+use crate::error::Result;
+use crate::path::{to_json_string, GronDialect, Identifiers, Path, Segment};
+
+/// Writes values as `gron` assignments, e.g. `json.foo.bar = "baz";`.
+pub(super) struct GronWriter<'a> {
+    writer: &'a mut dyn Write,
+    buffer: Buffer,
+    dialect: GronDialect,
+    /// Path of the last written value. Containers on this path have already been declared.
+    last_path: Option<Vec<Segment>>,
+    /// Whether the values written come from a YAML merge key.
+    merged: bool,
+    /// Name of the file of the values written, printed before each line.
+    file: Option<String>,
+    options: WriterOptions,
+    /// Number of values selected by the filter.
+    matches: usize,
+}
+
+impl<'a> GronWriter<'a> {
+    pub(super) fn new(
+        writer: &'a mut dyn Write,
+        color_choice: ColorChoice,
+        identifiers: Identifiers,
+        options: WriterOptions,
+    ) -> Self {
+        let buffer = BufferWriter::stdout(color_choice).buffer();
+        Self {
+            writer,
+            buffer,
+            dialect: GronDialect(identifiers),
+            last_path: None,
+            merged: false,
+            file: None,
+            options,
+            matches: 0,
+        }
+    }
+
+    /// Writes an assignment. If `merged` is true, it is followed by a comment saying that the
+    /// value comes from a YAML merge key. Returns whether the filter selected it.
+    fn write_statement(
+        &mut self,
+        path: &[Segment],
+        value: &str,
+        value_color_spec: Option<&ColorSpec>,
+        merged: bool,
+    ) -> Result<bool> {
+        if self.options.is_done(self.matches) {
+            return Ok(false);
+        }
+
+        let path = Path::from(path.to_vec()).display(&self.dialect).to_string();
+        let mut parts = vec![
+            (path.as_str(), Some(&*KEY_COLOR_SPEC)),
+            (" = ", None),
+            (value, value_color_spec),
+            (";", None),
+        ];
+        if merged {
+            parts.push((" // merged", None));
+        }
+
+        write_line_parts(
+            &mut self.buffer,
+            &self.options,
+            self.file.as_deref(),
+            &parts,
+            0,
+            2,
+        )
+    }
+
+    /// Declares the containers on the path that weren't declared by a previous value.
+    fn write_containers(&mut self, path: &[Segment]) -> Result<()> {
+        let declared = match &self.last_path {
+            Some(last) if !last.is_empty() => {
+                let common = last.iter().zip(path).take_while(|(a, b)| a == b).count();
+                common.min(last.len() - 1) + 1
+            }
+            _ => 0,
+        };
+
+        for i in declared..path.len() {
+            let container = match path[i] {
+                Segment::Key(_) | Segment::OtherKey(_) => "{}",
+                Segment::Index(_) => "[]",
+            };
+            self.write_statement(&path[..i], container, Some(&RAW_VALUE_COLOR_SPEC), false)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_value(
+        &mut self,
+        path: &Path,
+        value: &str,
+        value_color_spec: Option<&ColorSpec>,
+    ) -> Result<()> {
+        let path: Vec<Segment> = path.iter().cloned().collect();
+
+        self.write_containers(&path)?;
+        if self.write_statement(&path, value, value_color_spec, self.merged)? {
+            self.matches += 1;
+        }
+
+        let value = str::from_utf8(self.buffer.as_slice())?;
+        write!(self.writer, "{}", value)?;
+        self.buffer.clear();
+
+        self.last_path = Some(path);
+
+        Ok(())
+    }
+}
+
+/// Writes a number as JavaScript, which has no literals for the floats that JSON doesn't have,
+/// but has globals.
+fn format_number(value: &Number, normalize_numbers: bool) -> String {
+    let value = if normalize_numbers {
+        value.value()
+    } else {
+        value
+    };
+    match value {
+        Number::Float(x) if x.is_nan() => "NaN".to_string(),
+        Number::Float(x) if x.is_infinite() && *x > 0.0 => "Infinity".to_string(),
+        Number::Float(x) if x.is_infinite() => "-Infinity".to_string(),
+        n => n.to_string(),
+    }
+}
+
+impl ValueWriter for GronWriter<'_> {
+    fn write_string(&mut self, path: &Path, value: &str) -> Result<()> {
+        self.write_value(path, &to_json_string(value), Some(&STRING_VALUE_COLOR_SPEC))
+    }
+
+    fn write_number(&mut self, path: &Path, value: &Number) -> Result<()> {
+        let value = format_number(value, self.options.normalize_numbers);
+        self.write_value(path, &value, None)
+    }
+
+    fn write_bool(&mut self, path: &Path, value: bool) -> Result<()> {
+        self.write_value(path, &value.to_string(), None)
+    }
+
+    fn write_null(&mut self, path: &Path) -> Result<()> {
+        self.write_value(path, "null", Some(&NULL_VALUE_COLOR_SPEC))
+    }
+
+    fn write_datetime(&mut self, path: &Path, value: &Datetime) -> Result<()> {
+        self.write_value(
+            path,
+            &serde_json::to_string(&value.to_string())?,
+            Some(&DATETIME_VALUE_COLOR_SPEC),
+        )
+    }
+
+    fn write_raw(&mut self, path: &Path, value: &str) -> Result<()> {
+        self.write_value(path, value, Some(&RAW_VALUE_COLOR_SPEC))
+    }
+
+    /// gron output is JavaScript, which has no tags.
+    fn write_tag(&mut self, _path: &Path, _tag: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Containers are assigned as JSON, which is also JavaScript.
+    fn write_collapsed(&mut self, path: &Path, value: &Node) -> Result<()> {
+        let value = collapsed_value(value, false, self.options.normalize_numbers);
+        self.write_value(path, &value, Some(&RAW_VALUE_COLOR_SPEC))
+    }
+
+    /// gron output is read back as a document, which excluded values can't be part of.
+    fn write_excluded(&mut self, _path: &Path, _leaves: usize) -> Result<()> {
+        Ok(())
+    }
+
+    /// Aliases are written as an assignment of the value at their anchor, e.g. `json.b = json.a;`.
+    fn write_alias(&mut self, path: &Path, _name: &str, anchor: &Path) -> Result<()> {
+        let anchor = anchor.display(&self.dialect).to_string();
+        self.write_value(path, &anchor, Some(&KEY_COLOR_SPEC))
+    }
+
+    fn set_merged(&mut self, merged: bool) {
+        self.merged = merged;
+    }
+
+    /// The containers of the values of each file are declared again.
+    fn set_file(&mut self, file: Option<String>) {
+        self.file = file;
+        self.last_path = None;
+    }
+
+    fn matches(&self) -> usize {
+        self.matches
+    }
+
+    fn is_done(&self) -> bool {
+        self.options.is_done(self.matches)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+#[cfg(test)]
+mod test_gron_writer {
+    use crate::document::{Node, Number};
+    use termcolor::ColorChoice;
+
+    use super::{GronWriter, ValueWriter, WriterOptions};
+    use crate::path::{Identifiers, JqDialect, Path, PathDialect};
+
+    fn path(s: &str) -> Path {
+        JqDialect.parse(s).unwrap().0
+    }
+
+    static FORMAT_RESET: &str = "\u{1b}[0m";
+    static COLOR_GREEN: &str = "\u{1b}[32m";
+    static COLOR_BLUE: &str = "\u{1b}[34m";
+
+    fn write(f: impl FnOnce(&mut GronWriter)) -> String {
+        let mut buffer = Vec::new();
+        let mut writer = GronWriter::new(
+            &mut buffer,
+            ColorChoice::Never,
+            Identifiers::Unicode,
+            WriterOptions::default(),
+        );
+
+        f(&mut writer);
+
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn test_write_string_with_color() {
+        let mut buffer = Vec::new();
+        let mut writer = GronWriter::new(
+            &mut buffer,
+            ColorChoice::Always,
+            Identifiers::Unicode,
+            WriterOptions::default(),
+        );
+
+        writer
+            .write_string(&Path::default(), "Some String")
+            .unwrap();
+
+        let actual = std::str::from_utf8(buffer.as_slice()).unwrap();
+        let expected = format!(
+            "{}{}json{} = {}{}\"Some String\"{};\n",
+            FORMAT_RESET, COLOR_BLUE, FORMAT_RESET, FORMAT_RESET, COLOR_GREEN, FORMAT_RESET
+        );
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_write_scalars() {
+        let output = write(|w| {
+            w.write_string(&path("."), "Some \"String\"\t").unwrap();
+            w.write_number(&path("."), &Number::UInt(420)).unwrap();
+            w.write_number(&path("."), &Number::Float(-0.0)).unwrap();
+            w.write_number(&path("."), &"1.50".parse().unwrap())
+                .unwrap();
+            w.write_number(&path("."), &Number::Float(f64::INFINITY))
+                .unwrap();
+            w.write_number(&path("."), &Number::Float(f64::NEG_INFINITY))
+                .unwrap();
+            w.write_number(&path("."), &Number::Float(f64::NAN))
+                .unwrap();
+            w.write_bool(&path("."), true).unwrap();
+            w.write_null(&path(".")).unwrap();
+            w.write_raw(&path("."), "{}").unwrap();
+            w.write_datetime(&path("."), &"1979-05-27".parse().unwrap())
+                .unwrap();
+            w.write_alias(&path("."), "foo", &path(".foo")).unwrap();
+        });
+
+        let expected = concat!(
+            "json = \"Some \\\"String\\\"\\t\";\n",
+            "json = 420;\n",
+            "json = -0.0;\n",
+            "json = 1.50;\n",
+            "json = Infinity;\n",
+            "json = -Infinity;\n",
+            "json = NaN;\n",
+            "json = true;\n",
+            "json = null;\n",
+            "json = {};\n",
+            "json = \"1979-05-27\";\n",
+            "json = json.foo;\n",
+        );
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_write_collapsed_special_floats_as_strings() {
+        let value = Node::Array(vec![
+            Node::Number(Number::Float(f64::NAN)),
+            Node::Number(Number::Float(f64::INFINITY)),
+            Node::Number(Number::Float(f64::NEG_INFINITY)),
+        ]);
+        let output = write(|w| w.write_collapsed(&path(".a"), &value).unwrap());
+
+        // Collapsed values are JSON, which has no such floats.
+        let expected = concat!("json = {};\n", "json.a = [\"nan\",\"inf\",\"-inf\"];\n");
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_containers_are_declared_once() {
+        let output = write(|w| {
+            w.write_string(&path(".address.city"), "London").unwrap();
+            w.write_string(&path(".address.street"), "Downing Street")
+                .unwrap();
+            w.write_number(&path(".age"), &Number::UInt(43)).unwrap();
+            w.write_string(&path(".phones[0]"), "+44 1234567").unwrap();
+            w.write_string(&path(".phones[1]"), "+44 2345678").unwrap();
+            w.write_raw(&path(".tags"), "[]").unwrap();
+            w.write_bool(&path(".matrix[0][0]"), true).unwrap();
+            w.write_bool(&path(".matrix[1][0]"), false).unwrap();
+        });
+
+        let expected = concat!(
+            "json = {};\n",
+            "json.address = {};\n",
+            "json.address.city = \"London\";\n",
+            "json.address.street = \"Downing Street\";\n",
+            "json.age = 43;\n",
+            "json.phones = [];\n",
+            "json.phones[0] = \"+44 1234567\";\n",
+            "json.phones[1] = \"+44 2345678\";\n",
+            "json.tags = [];\n",
+            "json.matrix = [];\n",
+            "json.matrix[0] = [];\n",
+            "json.matrix[0][0] = true;\n",
+            "json.matrix[1] = [];\n",
+            "json.matrix[1][0] = false;\n",
+        );
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_keys_are_quoted_when_not_identifiers() {
+        let output = write(|w| {
+            w.write_null(&path(r#"."first name""#)).unwrap();
+            w.write_null(&path(r#"."123""#)).unwrap();
+            w.write_null(&path(".class")).unwrap();
+            w.write_null(&path(".libellé")).unwrap();
+            w.write_null(&path(r#"."say \"hi\"""#)).unwrap();
+        });
+
+        let expected = concat!(
+            "json = {};\n",
+            "json[\"first name\"] = null;\n",
+            "json[\"123\"] = null;\n",
+            "json[\"class\"] = null;\n",
+            "json.libellé = null;\n",
+            "json[\"say \\\"hi\\\"\"] = null;\n",
+        );
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_merged_values_are_commented() {
+        let output = write(|w| {
+            w.set_merged(true);
+            w.write_string(&path(".job.image"), "alpine").unwrap();
+            w.set_merged(false);
+            w.write_string(&path(".job.script"), "make").unwrap();
+        });
+
+        let expected = concat!(
+            "json = {};\n",
+            "json.job = {};\n",
+            "json.job.image = \"alpine\"; // merged\n",
+            "json.job.script = \"make\";\n",
+        );
+        assert_eq!(expected, output);
+    }
+}
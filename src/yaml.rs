@@ -0,0 +1,817 @@
+use std::collections::HashMap;
+
+use yaml_rust2::parser::{Event, Parser, Tag};
+use yaml_rust2::scanner::{Marker, Scanner, TScalarStyle, Token, TokenType};
+
+use crate::document::{decode_binary, Alias, Key, Mapping, Node, Number};
+use crate::error::{Error, ErrorKind, Result};
+use crate::path::Segment;
+
+/// The prefix of the tags of the YAML core schema, which `!!` is a shorthand for.
+const CORE_SCHEMA: &str = "tag:yaml.org,2002:";
+
+/// Number of nodes that aliases can copy, per byte of input. Aliases of aliases grow
+/// exponentially, e.g. in a "billion laughs" document, which would otherwise exhaust memory.
+pub(super) const ALIAS_EXPANSION_RATIO: usize = 100;
+
+/// How YAML documents are loaded.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct LoadOptions {
+    /// Loads aliases as `Node::Alias`, rather than replacing them by a copy of the value of
+    /// their anchor.
+    pub(super) keep_aliases: bool,
+    /// Wraps the values that mappings get from merge keys in `Node::Merged`.
+    pub(super) mark_merged: bool,
+}
+
+/// A container being loaded.
+enum Frame {
+    Sequence {
+        anchor: usize,
+        tag: Option<Tag>,
+        items: Vec<Node>,
+    },
+    Mapping {
+        anchor: usize,
+        tag: Option<Tag>,
+        object: Mapping,
+        /// The key of the entry whose value is being loaded.
+        key: Option<Key>,
+        /// Set while the value of a merge key (`<<`) is being loaded.
+        merging: bool,
+        /// The values of the merge keys, with the number of entries of the mapping before them.
+        merges: Vec<(usize, Node)>,
+    },
+}
+
+/// Loads all the documents of a YAML stream.
+///
+/// Documents are built from the events of the parser rather than through serde, which drops the
+/// tags of the core schema, e.g. `!!binary`, and doesn't apply merge keys.
+pub(super) fn load_documents(input: &str, options: LoadOptions) -> Result<Vec<Node>> {
+    let mut parser = Parser::new_from_str(input);
+    let mut documents = Vec::new();
+    // Copies of the anchored nodes, with their number of nodes, by anchor id.
+    let mut anchors: HashMap<usize, (Node, usize)> = HashMap::new();
+    // The number of nodes that aliases can still copy.
+    let mut expansion_budget = input.len().saturating_mul(ALIAS_EXPANSION_RATIO);
+    // The aliases of the anchors, when they are kept.
+    let mut aliases: HashMap<usize, Alias> = HashMap::new();
+    let anchor_names = if options.keep_aliases {
+        get_anchor_names(input)
+    } else {
+        Vec::new()
+    };
+    let mut stack: Vec<Frame> = Vec::new();
+
+    loop {
+        let (event, mark) = parser
+            .next_token()
+            .map_err(|e| Error::new(ErrorKind::YamlDeserialize, e))?;
+
+        let (node, anchor) = match event {
+            Event::Scalar(value, style, _, tag)
+                if is_merge_key(&value, style, tag.as_ref()) && expects_key(&stack) =>
+            {
+                if let Some(Frame::Mapping { merging, .. }) = stack.last_mut() {
+                    *merging = true;
+                }
+                continue;
+            }
+            Event::Scalar(value, style, anchor, tag) => {
+                (load_scalar(value, style, tag, mark)?, anchor)
+            }
+            Event::SequenceStart(anchor, tag) => {
+                stack.push(Frame::Sequence {
+                    anchor,
+                    tag,
+                    items: Vec::new(),
+                });
+                continue;
+            }
+            Event::MappingStart(anchor, tag) => {
+                stack.push(Frame::Mapping {
+                    anchor,
+                    tag,
+                    object: Mapping::new(),
+                    key: None,
+                    merging: false,
+                    merges: Vec::new(),
+                });
+                continue;
+            }
+            Event::SequenceEnd | Event::MappingEnd => match stack.pop() {
+                Some(Frame::Sequence { anchor, tag, items }) => {
+                    (apply_tag(Node::Array(items), tag), anchor)
+                }
+                Some(Frame::Mapping {
+                    anchor,
+                    tag,
+                    mut object,
+                    merges,
+                    ..
+                }) => {
+                    merge(&mut object, merges, options.mark_merged);
+                    (apply_tag(Node::Object(object), tag), anchor)
+                }
+                None => return Err(error("unexpected end of container", mark)),
+            },
+            // Merge keys need the value of the anchor, even when aliases are kept.
+            Event::Alias(id) => match aliases.get(&id) {
+                Some(alias) if !is_merge_value(&stack) => (Node::Alias(alias.clone()), 0),
+                _ => match anchors.get(&id) {
+                    Some((node, size)) => {
+                        expansion_budget = expansion_budget
+                            .checked_sub(*size)
+                            .ok_or_else(|| error("repetition limit exceeded", mark))?;
+                        (node.clone(), 0)
+                    }
+                    None => return Err(error("unknown anchor", mark)),
+                },
+            },
+            Event::StreamEnd => break,
+            Event::Nothing | Event::StreamStart | Event::DocumentStart | Event::DocumentEnd => {
+                continue
+            }
+        };
+
+        // Anchor ids start at 1.
+        if anchor > 0 {
+            anchors.insert(anchor, (node.clone(), count_nodes(&node)));
+            if options.keep_aliases {
+                let alias = Alias {
+                    name: anchor_names.get(anchor - 1).cloned().unwrap_or_default(),
+                    anchor: get_current_path(&stack),
+                };
+                aliases.insert(anchor, alias);
+            }
+        }
+
+        match stack.last_mut() {
+            None => documents.push(node),
+            Some(Frame::Sequence { items, .. }) => items.push(node),
+            Some(Frame::Mapping {
+                object,
+                key,
+                merging,
+                merges,
+                ..
+            }) => {
+                if std::mem::take(merging) {
+                    if !is_mergeable(&node) {
+                        return Err(error(
+                            "merge keys expect a mapping or a sequence of mappings",
+                            mark,
+                        ));
+                    }
+                    merges.push((object.len(), node));
+                } else if let Some(k) = key.take() {
+                    object.insert(k, node);
+                } else {
+                    *key = Some(Key::from(node));
+                }
+            }
+        }
+    }
+
+    // Like an empty JSON document, an empty stream is null.
+    if documents.is_empty() {
+        documents.push(Node::Null);
+    }
+
+    Ok(documents)
+}
+
+/// Counts the nodes of a value, including itself.
+pub(super) fn count_nodes(node: &Node) -> usize {
+    let mut stack = vec![node];
+    let mut count = 0;
+
+    while let Some(node) = stack.pop() {
+        count += 1;
+        match node {
+            Node::Array(a) => stack.extend(a),
+            Node::Object(o) => stack.extend(o.values()),
+            Node::Tagged(_, value) | Node::Merged(value) => stack.push(value),
+            _ => {}
+        }
+    }
+
+    count
+}
+
+/// A plain `<<` key, or one with the `!!merge` tag.
+fn is_merge_key(value: &str, style: TScalarStyle, tag: Option<&Tag>) -> bool {
+    let is_merge_tag = match tag {
+        Some(tag) => tag.handle == CORE_SCHEMA && tag.suffix == "merge",
+        None => style == TScalarStyle::Plain,
+    };
+    value == "<<" && is_merge_tag
+}
+
+fn expects_key(stack: &[Frame]) -> bool {
+    matches!(
+        stack.last(),
+        Some(Frame::Mapping {
+            key: None,
+            merging: false,
+            ..
+        })
+    )
+}
+
+/// Returns whether the node being loaded is the value of a merge key, or an item of it.
+fn is_merge_value(stack: &[Frame]) -> bool {
+    matches!(
+        stack,
+        [.., Frame::Mapping { merging: true, .. }]
+            | [
+                ..,
+                Frame::Mapping { merging: true, .. },
+                Frame::Sequence { .. }
+            ]
+    )
+}
+
+fn is_mergeable(node: &Node) -> bool {
+    match node {
+        Node::Object(_) => true,
+        Node::Array(items) => items.iter().all(|item| matches!(item, Node::Object(_))),
+        _ => false,
+    }
+}
+
+/// Adds the entries of the values of merge keys to a mapping, where the merge keys were. The
+/// entries of the mapping itself take precedence, then those of the first merged mappings.
+fn merge(object: &mut Mapping, merges: Vec<(usize, Node)>, mark_merged: bool) {
+    let mut inserted = 0;
+
+    for (position, value) in merges {
+        let mappings = match value {
+            Node::Array(items) => items,
+            mapping => vec![mapping],
+        };
+
+        for mapping in mappings {
+            let entries = match mapping {
+                Node::Object(entries) => entries,
+                _ => continue,
+            };
+
+            for (key, value) in entries {
+                if object.contains_key(&key) {
+                    continue;
+                }
+
+                let value = match value {
+                    Node::Merged(_) => value,
+                    value if mark_merged => Node::Merged(Box::new(value)),
+                    value => value,
+                };
+                object.shift_insert(position + inserted, key, value);
+                inserted += 1;
+            }
+        }
+    }
+}
+
+/// Returns the names of the anchors of a YAML stream. The parser numbers anchors in the order
+/// they appear, so the name of the anchor with id `n` is at `n - 1`.
+fn get_anchor_names(input: &str) -> Vec<String> {
+    Scanner::new(input.chars())
+        .filter_map(|Token(_, token)| match token {
+            TokenType::Anchor(name) => Some(name),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the path of the node being loaded, from the root of its document. Nodes within a
+/// complex key or the value of a merge key get the path of the mapping.
+fn get_current_path(stack: &[Frame]) -> Vec<Segment> {
+    let mut path = Vec::new();
+
+    for frame in stack {
+        match frame {
+            Frame::Sequence { items, .. } => path.push(Segment::Index(items.len())),
+            Frame::Mapping {
+                key: Some(k),
+                merging: false,
+                ..
+            } => path.push(Segment::from(k.clone())),
+            Frame::Mapping { .. } => break,
+        }
+    }
+
+    path
+}
+
+fn error(message: &str, mark: Marker) -> Error {
+    Error::new(
+        ErrorKind::YamlDeserialize,
+        format!(
+            "{} at line {} column {}",
+            message,
+            mark.line(),
+            mark.col() + 1
+        ),
+    )
+}
+
+/// Returns how a tag is written, e.g. `!Ref` or `!!binary`.
+fn tag_name(tag: &Tag) -> String {
+    match tag.handle.as_str() {
+        CORE_SCHEMA => format!("!!{}", tag.suffix),
+        "!" => format!("!{}", tag.suffix),
+        handle => format!("!<{}{}>", handle, tag.suffix),
+    }
+}
+
+/// Tags a container, unless the tag is the one it would have anyway.
+fn apply_tag(node: Node, tag: Option<Tag>) -> Node {
+    match tag {
+        Some(tag) if tag.handle == CORE_SCHEMA && matches!(tag.suffix.as_str(), "seq" | "map") => {
+            node
+        }
+        Some(tag) => Node::Tagged(tag_name(&tag), Box::new(node)),
+        None => node,
+    }
+}
+
+fn load_scalar(value: String, style: TScalarStyle, tag: Option<Tag>, mark: Marker) -> Result<Node> {
+    let is_plain = style == TScalarStyle::Plain;
+
+    let tag = match tag {
+        Some(tag) => tag,
+        None if is_plain => return Ok(resolve_plain_scalar(value)),
+        None => return Ok(Node::String(value)),
+    };
+
+    if tag.handle == CORE_SCHEMA {
+        let node = match tag.suffix.as_str() {
+            "str" => return Ok(Node::String(value)),
+            "binary" => {
+                return match decode_binary(&value) {
+                    Ok(bytes) => Ok(Node::Binary(bytes)),
+                    Err(e) => Err(error(&format!("invalid !!binary value: {}", e), mark)),
+                };
+            }
+            "null" | "bool" | "int" | "float" => resolve_plain_scalar(value.clone()),
+            _ if is_plain => return Ok(apply_tag(resolve_plain_scalar(value), Some(tag))),
+            _ => return Ok(apply_tag(Node::String(value), Some(tag))),
+        };
+
+        return match (tag.suffix.as_str(), node) {
+            ("null", Node::Null) => Ok(Node::Null),
+            ("bool", Node::Bool(b)) => Ok(Node::Bool(b)),
+            ("int", Node::Number(n)) if matches!(n.value(), Number::Int(_) | Number::UInt(_)) => {
+                Ok(Node::Number(n))
+            }
+            ("float", Node::Number(n)) => match n.value() {
+                Number::Int(i) => Ok(Node::Number(Number::Float(*i as f64))),
+                Number::UInt(u) => Ok(Node::Number(Number::Float(*u as f64))),
+                _ => Ok(Node::Number(n)),
+            },
+            _ => Err(error(
+                &format!("invalid value {:?} for !!{}", value, tag.suffix),
+                mark,
+            )),
+        };
+    }
+
+    // The non-specific tag `!` makes a plain scalar a string.
+    if tag.handle.is_empty() && tag.suffix == "!" {
+        return Ok(Node::String(value));
+    }
+
+    let node = if is_plain {
+        resolve_plain_scalar(value)
+    } else {
+        Node::String(value)
+    };
+
+    Ok(apply_tag(node, Some(tag)))
+}
+
+/// Resolves the type of an untagged plain scalar, following the same rules as `serde_yaml`.
+fn resolve_plain_scalar(value: String) -> Node {
+    match value.as_str() {
+        "" | "~" | "null" | "Null" | "NULL" => return Node::Null,
+        "true" | "True" | "TRUE" => return Node::Bool(true),
+        "false" | "False" | "FALSE" => return Node::Bool(false),
+        _ => {}
+    }
+
+    match parse_number(&value) {
+        Some(n) => Node::Number(n),
+        None => Node::String(value),
+    }
+}
+
+/// Parses a plain scalar that YAML resolves to a number, e.g. `0x1f` or `.inf`, keeping its text.
+pub(super) fn parse_number(s: &str) -> Option<Number> {
+    let n = match parse_int(s) {
+        Some(n) => n,
+        None if is_digits_but_not_number(s) => return None,
+        None => Number::Float(parse_float(s)?),
+    };
+
+    Some(n.with_literal(s))
+}
+
+/// Parses decimal, hexadecimal (`0x`), octal (`0o`) and binary (`0b`) integers.
+fn parse_int(s: &str) -> Option<Number> {
+    let (negative, unsigned) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+
+    let (radix, digits) = if let Some(digits) = unsigned.strip_prefix("0x") {
+        (16, digits)
+    } else if let Some(digits) = unsigned.strip_prefix("0o") {
+        (8, digits)
+    } else if let Some(digits) = unsigned.strip_prefix("0b") {
+        (2, digits)
+    } else if is_digits_but_not_number(s) {
+        return None;
+    } else {
+        (10, unsigned)
+    };
+
+    if digits.starts_with(['+', '-']) {
+        return None;
+    }
+    let n = u64::from_str_radix(digits, radix).ok()?;
+
+    match negative {
+        false => Some(Number::UInt(n)),
+        true if n == 0 => Some(Number::UInt(0)),
+        true if n <= 1 << 63 => Some(Number::Int((n as i64).wrapping_neg())),
+        true => None,
+    }
+}
+
+fn parse_float(s: &str) -> Option<f64> {
+    let unsigned = match s.strip_prefix('+') {
+        Some(rest) if rest.starts_with(['+', '-']) => return None,
+        Some(rest) => rest,
+        None => s,
+    };
+
+    match unsigned {
+        ".inf" | ".Inf" | ".INF" => return Some(f64::INFINITY),
+        "-.inf" | "-.Inf" | "-.INF" => return Some(f64::NEG_INFINITY),
+        ".nan" | ".NaN" | ".NAN" => return Some(f64::NAN),
+        _ => {}
+    }
+
+    unsigned.parse::<f64>().ok().filter(|x| x.is_finite())
+}
+
+/// Leading zeros followed by digits make a string in YAML 1.2, e.g. `0123`.
+fn is_digits_but_not_number(s: &str) -> bool {
+    let s = s.strip_prefix(['-', '+']).unwrap_or(s);
+    s.len() > 1 && s.starts_with('0') && s[1..].bytes().all(|b| b.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod test_load_documents {
+    use serde_json::json;
+
+    use super::{load_documents, LoadOptions};
+    use crate::document::{Alias, Key, Mapping, Node, Number};
+    use crate::path::Segment;
+
+    fn load(input: &str) -> Node {
+        let mut documents = load_documents(input, LoadOptions::default()).unwrap();
+        assert_eq!(documents.len(), 1);
+        documents.remove(0)
+    }
+
+    fn literal(text: &str, n: Number) -> Node {
+        Node::Number(Number::Literal(text.to_string(), Box::new(n)))
+    }
+
+    fn tagged(tag: &str, node: Node) -> Node {
+        Node::Tagged(tag.to_string(), Box::new(node))
+    }
+
+    #[test]
+    fn test_plain_scalars() {
+        let node = load(concat!(
+            "[~, null, '', true, False, 42, -42, +1, 0x1f, -0o17, 0b11, 012, 1.5, 1e3, ",
+            "-.inf, 1_000, 'true', \"1\", yes]",
+        ));
+
+        assert_eq!(
+            node,
+            Node::Array(vec![
+                Node::Null,
+                Node::Null,
+                Node::String("".to_string()),
+                Node::Bool(true),
+                Node::Bool(false),
+                Node::Number(Number::UInt(42)),
+                Node::Number(Number::Int(-42)),
+                literal("+1", Number::UInt(1)),
+                literal("0x1f", Number::UInt(31)),
+                literal("-0o17", Number::Int(-15)),
+                literal("0b11", Number::UInt(3)),
+                Node::String("012".to_string()),
+                Node::Number(Number::Float(1.5)),
+                literal("1e3", Number::Float(1000.0)),
+                Node::Number(Number::Float(f64::NEG_INFINITY)),
+                Node::String("1_000".to_string()),
+                Node::String("true".to_string()),
+                Node::String("1".to_string()),
+                Node::String("yes".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_same_documents_as_serde_yaml() {
+        let input = std::fs::read_to_string("./tests/example.yaml").unwrap();
+        let expected: serde_json::Value = serde_yaml::from_str(&input).unwrap();
+
+        assert_eq!(load(&input), Node::from(expected));
+    }
+
+    #[test]
+    fn test_multiple_documents() {
+        assert_eq!(
+            load_documents("---\na: 1\n---\n- b\n...\n", LoadOptions::default()).unwrap(),
+            [Node::from(json!({"a": 1})), Node::from(json!(["b"])),]
+        );
+        assert_eq!(
+            load_documents("", LoadOptions::default()).unwrap(),
+            [Node::Null]
+        );
+        assert_eq!(
+            load_documents("# comment\n", LoadOptions::default()).unwrap(),
+            [Node::Null]
+        );
+    }
+
+    #[test]
+    fn test_custom_tags() {
+        let node = load(concat!(
+            "Name: !Sub \"${Env}-bucket\"\n",
+            "Arn: !GetAtt [Bucket, Arn]\n",
+            "Port: !Ref 8080\n",
+            "If: !If {a: b}\n",
+            "Str: ! 42\n",
+            "Verbatim: !<tag:example.com,2000:x> y\n",
+        ));
+
+        let expected = Node::Object(
+            vec![
+                (
+                    Key::String("Name".to_string()),
+                    tagged("!Sub", Node::String("${Env}-bucket".to_string())),
+                ),
+                (
+                    Key::String("Arn".to_string()),
+                    tagged("!GetAtt", Node::from(json!(["Bucket", "Arn"]))),
+                ),
+                (
+                    Key::String("Port".to_string()),
+                    tagged("!Ref", Node::Number(Number::UInt(8080))),
+                ),
+                (
+                    Key::String("If".to_string()),
+                    tagged("!If", Node::from(json!({"a": "b"}))),
+                ),
+                (
+                    Key::String("Str".to_string()),
+                    Node::String("42".to_string()),
+                ),
+                (
+                    Key::String("Verbatim".to_string()),
+                    tagged("!<tag:example.com,2000:x>", Node::String("y".to_string())),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(node, expected);
+    }
+
+    #[test]
+    fn test_core_schema_tags() {
+        let node = load(concat!(
+            "- !!str 42\n",
+            "- !!int \"42\"\n",
+            "- !!float 1\n",
+            "- !!bool 'true'\n",
+            "- !!null ''\n",
+            "- !!map {a: 1}\n",
+            "- !!set {a}\n",
+        ));
+
+        assert_eq!(
+            node,
+            Node::Array(vec![
+                Node::String("42".to_string()),
+                Node::Number(Number::UInt(42)),
+                Node::Number(Number::Float(1.0)),
+                Node::Bool(true),
+                Node::Null,
+                Node::from(json!({"a": 1})),
+                tagged("!!set", Node::from(json!({"a": null}))),
+            ])
+        );
+
+        assert!(load_documents("!!int foo", LoadOptions::default()).is_err());
+        assert!(load_documents("!!bool 1", LoadOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_binary() {
+        let node = load("data: !!binary |\n  SGVsbG8s\n  IHdvcmxk\n");
+        assert_eq!(
+            node.get("data"),
+            Some(&Node::Binary(b"Hello, world".to_vec()))
+        );
+
+        let err = load_documents("data: !!binary '%%%'", LoadOptions::default()).unwrap_err();
+        assert!(
+            err.to_string().contains("invalid !!binary value"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_aliases() {
+        let node = load("a: &x {b: [1]}\nc: *x\n");
+
+        assert_eq!(node, Node::from(json!({"a": {"b": [1]}, "c": {"b": [1]}})));
+        assert!(load_documents("a: *x", LoadOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_billion_laughs() {
+        let mut input = "a: &a [lol, lol, lol, lol, lol, lol, lol, lol, lol]\n".to_string();
+        for (name, prev) in ["b", "c", "d", "e", "f", "g", "h", "i"]
+            .iter()
+            .zip("abcdefgh".chars())
+        {
+            input.push_str(&format!(
+                "{}: &{} [*{p}, *{p}, *{p}, *{p}, *{p}, *{p}, *{p}, *{p}, *{p}]\n",
+                name,
+                name,
+                p = prev
+            ));
+        }
+
+        let err = load_documents(&input, LoadOptions::default()).unwrap_err();
+        assert!(
+            err.to_string().contains("repetition limit exceeded"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_kept_aliases() {
+        let alias = |name: &str, anchor: Vec<Segment>| {
+            Node::Alias(Alias {
+                name: name.to_string(),
+                anchor,
+            })
+        };
+
+        let options = LoadOptions {
+            keep_aliases: true,
+            ..LoadOptions::default()
+        };
+        let mut documents = load_documents(
+            "a: &x {b: [&y 1, *y]}\nc: [*x]\n---\n- &z foo\n- *z\n",
+            options,
+        )
+        .unwrap();
+
+        let key = |k: &str| Segment::Key(k.to_string());
+        let b = Node::Array(vec![
+            Node::Number(Number::UInt(1)),
+            alias("y", vec![key("a"), key("b"), Segment::Index(0)]),
+        ]);
+        let expected = Node::Object(
+            vec![
+                (
+                    Key::String("a".to_string()),
+                    Node::Object(
+                        vec![(Key::String("b".to_string()), b)]
+                            .into_iter()
+                            .collect(),
+                    ),
+                ),
+                (
+                    Key::String("c".to_string()),
+                    Node::Array(vec![alias("x", vec![key("a")])]),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(documents.remove(0), expected);
+
+        assert_eq!(
+            documents.remove(0),
+            Node::Array(vec![
+                Node::String("foo".to_string()),
+                alias("z", vec![Segment::Index(0)]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_merge_keys() {
+        let node = load(concat!(
+            "a: &a {x: 1, y: 1}\n",
+            "b: &b {y: 2, z: 2}\n",
+            "c:\n",
+            "  w: 3\n",
+            "  <<: [*a, *b]\n",
+            "  x: 3\n",
+            "d: {<<: *b, \"<<\": quoted}\n",
+        ));
+
+        let c = Node::from(json!({"w": 3, "x": 3, "y": 1, "z": 2}));
+        assert_eq!(node.get("c"), Some(&c));
+        let keys = match node.get("c") {
+            Some(Node::Object(o)) => o.keys().map(|k| k.as_str()).collect::<Vec<_>>(),
+            _ => panic!("expected an object"),
+        };
+        assert_eq!(keys, ["w", "y", "z", "x"]);
+
+        let d = Node::from(json!({"y": 2, "z": 2, "<<": "quoted"}));
+        assert_eq!(node.get("d"), Some(&d));
+
+        assert!(load_documents("a: {<<: 1}", LoadOptions::default()).is_err());
+        assert!(load_documents("a: {<<: [{}, 1]}", LoadOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_marked_merged_values() {
+        let options = LoadOptions {
+            mark_merged: true,
+            keep_aliases: true,
+        };
+        let node = load_documents("a: &a {x: 1}\nb: {<<: *a, y: 2}\n", options)
+            .unwrap()
+            .remove(0);
+
+        let expected: Mapping = vec![
+            (
+                Key::String("x".to_string()),
+                Node::Merged(Box::new(Node::Number(Number::UInt(1)))),
+            ),
+            (Key::String("y".to_string()), Node::Number(Number::UInt(2))),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(node.get("b"), Some(&Node::Object(expected)));
+    }
+
+    #[test]
+    fn test_keys_that_are_not_strings() {
+        let node = load("1: one\ntrue: yes\n~: nothing\n1.5: half\n\"2\": two\n!Ref x: y\n");
+
+        let keys = match node {
+            Node::Object(o) => o.keys().cloned().collect::<Vec<_>>(),
+            _ => panic!("expected an object"),
+        };
+        assert_eq!(
+            keys,
+            [
+                Key::Other("1".to_string()),
+                Key::Other("true".to_string()),
+                Key::Other("null".to_string()),
+                Key::Other("1.5".to_string()),
+                Key::String("2".to_string()),
+                Key::Other("!Ref x".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_complex_keys() {
+        let input =
+            "[a, 1]: seq\n{b: [\"true\", \"c d\"], e: {}}: map\n? [\"x, y\", \"\"]\n: quoted";
+
+        let keys = match load(input) {
+            Node::Object(o) => o.keys().cloned().collect::<Vec<_>>(),
+            _ => panic!("expected an object"),
+        };
+        assert_eq!(
+            keys,
+            [
+                Key::Other("[a, 1]".to_string()),
+                Key::Other(r#"{b: ["true", c d], e: {}}"#.to_string()),
+                Key::Other(r#"["x, y", ""]"#.to_string()),
+            ]
+        );
+    }
+}
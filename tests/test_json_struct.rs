@@ -15,20 +15,107 @@ mod test_parse_stdin {
 
         let stdout = String::from_utf8(output).unwrap();
         let expected_output = concat!(
-            ".alias.bar => \"baz\"\n",
-            ".alias_reuse.bar => \"baz\"\n",
-            ".content => \"Or we\\ncan auto\\nconvert line breaks\\nto save space\"\n",
             ".json[0] => \"rigid\"\n",
             ".json[1] => \"better for data interchange\"\n",
+            ".yaml[0] => \"slim and flexible\"\n",
+            ".yaml[1] => \"better for configuration\"\n",
+            ".object.key => \"value\"\n",
             ".object.array[0].null_value => null\n",
             ".object.array[1].boolean => true\n",
             ".object.array[2].integer => 1\n",
             ".object.array[3].alias => \"aliases are like variables\"\n",
             ".object.array[4].alias => \"aliases are like variables\"\n",
-            ".object.key => \"value\"\n",
             ".paragraph => \"Blank lines denote\\nparagraph breaks\\n\"\n",
-            ".yaml[0] => \"slim and flexible\"\n",
-            ".yaml[1] => \"better for configuration\"\n",
+            ".content => \"Or we\\ncan auto\\nconvert line breaks\\nto save space\"\n",
+            ".alias.bar => \"baz\"\n",
+            ".alias_reuse.bar => \"baz\"\n",
+        );
+
+        assert_eq!(stdout, expected_output);
+    }
+
+    #[test]
+    fn test_deeply_nested_json() {
+        let depth = 100_000;
+        let input = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .write_stdin(input)
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        let expected_output = format!(".{} => []\n", "[0]".repeat(depth - 1));
+
+        assert_eq!(stdout, expected_output);
+    }
+
+    #[test]
+    fn test_deeply_nested_jsonl() {
+        let depth = 100_000;
+        let input = format!("{}{}\n{{\"a\": 1}}\n", "[".repeat(depth), "]".repeat(depth));
+
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["-t", "jsonl"])
+            .write_stdin(input)
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        let expected_output = format!("[0]{} => []\n[1].a => 1\n", "[0]".repeat(depth - 1));
+
+        assert_eq!(stdout, expected_output);
+    }
+
+    #[test]
+    fn test_jsonl_one_value_per_line() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["-t", "jsonl"])
+            .write_stdin("{\"a\": 1}\n{\"b\": 2} {\"c\": 3}\n")
+            .assert();
+
+        let output = assert.get_output().clone();
+        assert.code(2);
+
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert_eq!(
+            stderr,
+            "Error: (standard input): JsonDeserialize: record 2: trailing characters at line 2 column 10\n"
+        );
+    }
+
+    #[test]
+    fn test_jsonl() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["-t", "jsonl"])
+            .pipe_stdin("./tests/example.jsonl")
+            .unwrap()
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        let expected_output = concat!(
+            "[0].level => \"info\"\n",
+            "[0].user.id => 42\n",
+            "[0].user.name => \"John Doe\"\n",
+            "[0].tags[0] => \"login\"\n",
+            "[1].level => \"warn\"\n",
+            "[1].user.id => 43\n",
+            "[1].user.name => \"Jane Doe\"\n",
+            "[1].tags => []\n",
+            "[2] => \"just a string\"\n",
+            "[3][0] => 1\n",
+            "[3][1] => 2\n",
         );
 
         assert_eq!(stdout, expected_output);
@@ -48,20 +135,281 @@ mod test_parse_stdin {
 
         let stdout = String::from_utf8(output).unwrap();
         let expected_output = concat!(
-            ".alias.bar => \"baz\"\n",
-            ".alias_reuse.bar => \"baz\"\n",
-            ".content => \"Or we\\ncan auto\\nconvert line breaks\\nto save space\"\n",
             ".json[0] => \"rigid\"\n",
             ".json[1] => \"better for data interchange\"\n",
+            ".yaml[0] => \"slim and flexible\"\n",
+            ".yaml[1] => \"better for configuration\"\n",
+            ".object.key => \"value\"\n",
             ".object.array[0].null_value => null\n",
             ".object.array[1].boolean => true\n",
             ".object.array[2].integer => 1\n",
             ".object.array[3].alias => \"aliases are like variables\"\n",
             ".object.array[4].alias => \"aliases are like variables\"\n",
-            ".object.key => \"value\"\n",
             ".paragraph => \"Blank lines denote\\nparagraph breaks\\n\"\n",
-            ".yaml[0] => \"slim and flexible\"\n",
-            ".yaml[1] => \"better for configuration\"\n",
+            ".content => \"Or we\\ncan auto\\nconvert line breaks\\nto save space\"\n",
+            ".alias.bar => \"baz\"\n",
+            ".alias_reuse.bar => \"baz\"\n",
+        );
+
+        assert_eq!(stdout, expected_output);
+    }
+
+    #[test]
+    fn test_yaml_keys_that_are_not_strings() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["-t", "yaml"])
+            .write_stdin("responses:\n  200: OK\n  \"200\": string\n  true: yes\n  [a, b]: seq\n")
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        let expected_output = concat!(
+            ".responses.200 => \"OK\"\n",
+            ".responses.\"200\" => \"string\"\n",
+            ".responses.true => \"yes\"\n",
+            ".responses[[a, b]] => \"seq\"\n",
+        );
+
+        assert_eq!(stdout, expected_output);
+    }
+
+    #[test]
+    fn test_yaml_tags() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["-t", "yaml"])
+            .write_stdin(concat!(
+                "Resources:\n",
+                "  Bucket:\n",
+                "    Properties:\n",
+                "      Name: !Sub \"${Env}-bucket\"\n",
+                "      Arn: !GetAtt [Bucket, Arn]\n",
+                "      Key: !!binary SGVsbG8=\n",
+                "      Password: !vault |\n",
+                "        $ANSIBLE_VAULT;1.1;AES256\n",
+            ))
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        let expected_output = concat!(
+            ".Resources.Bucket.Properties.Name => !Sub \"${Env}-bucket\"\n",
+            ".Resources.Bucket.Properties.Arn => !GetAtt\n",
+            ".Resources.Bucket.Properties.Arn[0] => \"Bucket\"\n",
+            ".Resources.Bucket.Properties.Arn[1] => \"Arn\"\n",
+            ".Resources.Bucket.Properties.Key => !!binary \"SGVsbG8=\"\n",
+            ".Resources.Bucket.Properties.Password => !vault \"$ANSIBLE_VAULT;1.1;AES256\\n\"\n",
+        );
+
+        assert_eq!(stdout, expected_output);
+    }
+
+    #[test]
+    fn test_number_literals() {
+        let input = r#"{"id": 1234567890123456789012, "price": 10.50, "count": 1e3}"#;
+
+        for (args, expected_output) in [
+            (
+                vec!["-t", "json"],
+                concat!(
+                    ".id => 1234567890123456789012\n",
+                    ".price => 10.50\n",
+                    ".count => 1e3\n",
+                ),
+            ),
+            (
+                vec!["-t", "json", "--normalize-numbers"],
+                concat!(
+                    ".id => 1.2345678901234568e21\n",
+                    ".price => 10.5\n",
+                    ".count => 1000.0\n",
+                ),
+            ),
+        ] {
+            let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .args(args)
+                .write_stdin(input)
+                .assert();
+
+            let output = assert.get_output().stdout.clone();
+            assert.success();
+
+            let stdout = String::from_utf8(output).unwrap();
+            assert_eq!(stdout, expected_output);
+        }
+    }
+
+    #[test]
+    fn test_unicode_keys() {
+        let input = r#"{"libellé": {"名前": 1}}"#;
+
+        for (args, expected_output) in [
+            // jq only reads ASCII identifiers.
+            (
+                vec!["-t", "json"],
+                concat!(r#"."libellé"."名前" => 1"#, "\n"),
+            ),
+            (
+                vec!["-t", "json", "--path-style", "jsonpath"],
+                "$.libellé.名前 => 1\n",
+            ),
+            (
+                vec!["-t", "json", "--gron"],
+                "json = {};\njson.libellé = {};\njson.libellé.名前 = 1;\n",
+            ),
+            (
+                vec!["-t", "json", "--path-style", "jsonpath", "--ascii-keys"],
+                "$['libellé']['名前'] => 1\n",
+            ),
+            (
+                vec!["-t", "json", "--gron", "--ascii-keys"],
+                concat!(
+                    "json = {};\n",
+                    "json[\"libellé\"] = {};\n",
+                    "json[\"libellé\"][\"名前\"] = 1;\n"
+                ),
+            ),
+        ] {
+            let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .args(args)
+                .write_stdin(input)
+                .assert();
+
+            let output = assert.get_output().stdout.clone();
+            assert.success();
+
+            let stdout = String::from_utf8(output).unwrap();
+            assert_eq!(stdout, expected_output);
+        }
+    }
+
+    #[test]
+    fn test_escaped_strings() {
+        let input = r#"{"a\\b": "tab\there \"quoted\" \u001b[31mred\\"}"#;
+
+        for (args, expected_output) in [
+            (
+                vec!["-t", "json"],
+                concat!(r#"."a\\b" => "tab\there \"quoted\" \u001b[31mred\\""#, "\n"),
+            ),
+            (
+                vec!["-t", "json", "--raw-strings"],
+                concat!(
+                    r#"."a\\b" => tab"#,
+                    "\t",
+                    "here \"quoted\" \u{1b}[31mred\\\n"
+                ),
+            ),
+        ] {
+            let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .args(args)
+                .write_stdin(input)
+                .assert();
+
+            let output = assert.get_output().stdout.clone();
+            assert.success();
+
+            let stdout = String::from_utf8(output).unwrap();
+            assert_eq!(stdout, expected_output);
+        }
+    }
+
+    #[test]
+    fn test_special_floats() {
+        for (data_type, input) in [
+            (
+                "yaml",
+                "max: .inf\nmin: -.inf\nthreshold: .nan\nzero: -0.0\n",
+            ),
+            (
+                "toml",
+                "max = inf\nmin = -inf\nthreshold = nan\nzero = -0.0\n",
+            ),
+        ] {
+            let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .args(["-t", data_type])
+                .write_stdin(input)
+                .assert();
+
+            let output = assert.get_output().stdout.clone();
+            assert.success();
+
+            let stdout = String::from_utf8(output).unwrap();
+            let expected_output = concat!(
+                ".max => inf\n",
+                ".min => -inf\n",
+                ".threshold => nan\n",
+                ".zero => -0.0\n",
+            );
+
+            assert_eq!(stdout, expected_output);
+        }
+    }
+
+    #[test]
+    fn test_yaml_merge_keys() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["-t", "yaml", "--mark-merged"])
+            .write_stdin(concat!(
+                ".defaults: &defaults\n",
+                "  image: alpine\n",
+                "  tags: [linux]\n",
+                "build:\n",
+                "  <<: *defaults\n",
+                "  image: debian\n",
+            ))
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        let expected_output = concat!(
+            ".\".defaults\".image => \"alpine\"\n",
+            ".\".defaults\".tags[0] => \"linux\"\n",
+            ".build.tags[0] => \"linux\" (merged)\n",
+            ".build.image => \"debian\"\n",
+        );
+
+        assert_eq!(stdout, expected_output);
+    }
+
+    #[test]
+    fn test_yaml_aliases_as_references() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["-t", "yaml", "--yaml-aliases", "reference"])
+            .write_stdin(concat!(
+                "defaults: &defaults\n",
+                "  image: alpine\n",
+                "  tags: [&linux linux]\n",
+                "jobs:\n",
+                "  - *defaults\n",
+                "  - image: debian\n",
+                "    tags: [*linux]\n",
+            ))
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        let expected_output = concat!(
+            ".defaults.image => \"alpine\"\n",
+            ".defaults.tags[0] => \"linux\"\n",
+            ".jobs[0] => *defaults (see .defaults)\n",
+            ".jobs[1].image => \"debian\"\n",
+            ".jobs[1].tags[0] => *linux (see .defaults.tags[0])\n",
         );
 
         assert_eq!(stdout, expected_output);
@@ -81,25 +429,25 @@ mod test_parse_stdin {
 
         let stdout = String::from_utf8(output).unwrap();
         let expected_output = concat!(
+            ".title => \"TOML Example\"\n",
+            ".owner.name => \"Tom Preston-Werner\"\n",
+            ".owner.dob => 1979-05-27T07:32:00-08:00\n",
+            ".database.server => \"192.168.1.1\"\n",
+            ".database.ports[0] => 8000\n",
+            ".database.ports[1] => 8001\n",
+            ".database.ports[2] => 8002\n",
+            ".database.connection_max => 5000\n",
+            ".database.enabled => true\n",
+            ".servers.alpha.ip => \"10.0.0.1\"\n",
+            ".servers.alpha.dc => \"eqdc10\"\n",
+            ".servers.beta.ip => \"10.0.0.2\"\n",
+            ".servers.beta.dc => \"eqdc10\"\n",
             ".clients.data[0][0] => \"gamma\"\n",
             ".clients.data[0][1] => \"delta\"\n",
             ".clients.data[1][0] => 1\n",
             ".clients.data[1][1] => 2\n",
             ".clients.hosts[0] => \"alpha\"\n",
             ".clients.hosts[1] => \"omega\"\n",
-            ".database.connection_max => 5000\n",
-            ".database.enabled => true\n",
-            ".database.ports[0] => 8000\n",
-            ".database.ports[1] => 8001\n",
-            ".database.ports[2] => 8002\n",
-            ".database.server => \"192.168.1.1\"\n",
-            ".owner.dob.\"$__toml_private_datetime\" => \"1979-05-27T07:32:00-08:00\"\n",
-            ".owner.name => \"Tom Preston-Werner\"\n",
-            ".servers.alpha.dc => \"eqdc10\"\n",
-            ".servers.alpha.ip => \"10.0.0.1\"\n",
-            ".servers.beta.dc => \"eqdc10\"\n",
-            ".servers.beta.ip => \"10.0.0.2\"\n",
-            ".title => \"TOML Example\"\n",
         );
 
         assert_eq!(stdout, expected_output);
@@ -122,20 +470,48 @@ mod test_parse_file {
 
         let stdout = String::from_utf8(output).unwrap();
         let expected_output = concat!(
-            ".alias.bar => \"baz\"\n",
-            ".alias_reuse.bar => \"baz\"\n",
-            ".content => \"Or we\\ncan auto\\nconvert line breaks\\nto save space\"\n",
             ".json[0] => \"rigid\"\n",
             ".json[1] => \"better for data interchange\"\n",
+            ".yaml[0] => \"slim and flexible\"\n",
+            ".yaml[1] => \"better for configuration\"\n",
+            ".object.key => \"value\"\n",
             ".object.array[0].null_value => null\n",
             ".object.array[1].boolean => true\n",
             ".object.array[2].integer => 1\n",
             ".object.array[3].alias => \"aliases are like variables\"\n",
             ".object.array[4].alias => \"aliases are like variables\"\n",
-            ".object.key => \"value\"\n",
             ".paragraph => \"Blank lines denote\\nparagraph breaks\\n\"\n",
-            ".yaml[0] => \"slim and flexible\"\n",
-            ".yaml[1] => \"better for configuration\"\n",
+            ".content => \"Or we\\ncan auto\\nconvert line breaks\\nto save space\"\n",
+            ".alias.bar => \"baz\"\n",
+            ".alias_reuse.bar => \"baz\"\n",
+        );
+
+        assert_eq!(stdout, expected_output);
+    }
+
+    #[test]
+    fn test_jsonl() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .arg("./tests/example.jsonl")
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        let expected_output = concat!(
+            "[0].level => \"info\"\n",
+            "[0].user.id => 42\n",
+            "[0].user.name => \"John Doe\"\n",
+            "[0].tags[0] => \"login\"\n",
+            "[1].level => \"warn\"\n",
+            "[1].user.id => 43\n",
+            "[1].user.name => \"Jane Doe\"\n",
+            "[1].tags => []\n",
+            "[2] => \"just a string\"\n",
+            "[3][0] => 1\n",
+            "[3][1] => 2\n",
         );
 
         assert_eq!(stdout, expected_output);
@@ -153,30 +529,30 @@ mod test_parse_file {
 
         let stdout = String::from_utf8(output).unwrap();
         let expected_output = concat!(
-            ".alias.bar => \"baz\"\n",
-            ".alias_reuse.bar => \"baz\"\n",
-            ".content => \"Or we\\ncan auto\\nconvert line breaks\\nto save space\"\n",
             ".json[0] => \"rigid\"\n",
             ".json[1] => \"better for data interchange\"\n",
+            ".yaml[0] => \"slim and flexible\"\n",
+            ".yaml[1] => \"better for configuration\"\n",
+            ".object.key => \"value\"\n",
             ".object.array[0].null_value => null\n",
             ".object.array[1].boolean => true\n",
             ".object.array[2].integer => 1\n",
             ".object.array[3].alias => \"aliases are like variables\"\n",
             ".object.array[4].alias => \"aliases are like variables\"\n",
-            ".object.key => \"value\"\n",
             ".paragraph => \"Blank lines denote\\nparagraph breaks\\n\"\n",
-            ".yaml[0] => \"slim and flexible\"\n",
-            ".yaml[1] => \"better for configuration\"\n",
+            ".content => \"Or we\\ncan auto\\nconvert line breaks\\nto save space\"\n",
+            ".alias.bar => \"baz\"\n",
+            ".alias_reuse.bar => \"baz\"\n",
         );
 
         assert_eq!(stdout, expected_output);
     }
 
     #[test]
-    fn test_toml() {
+    fn test_multi_document_yaml() {
         let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
             .unwrap()
-            .arg("./tests/example.toml")
+            .arg("./tests/example-multi.yaml")
             .assert();
 
         let output = assert.get_output().stdout.clone();
@@ -184,40 +560,25 @@ mod test_parse_file {
 
         let stdout = String::from_utf8(output).unwrap();
         let expected_output = concat!(
-            ".clients.data[0][0] => \"gamma\"\n",
-            ".clients.data[0][1] => \"delta\"\n",
-            ".clients.data[1][0] => 1\n",
-            ".clients.data[1][1] => 2\n",
-            ".clients.hosts[0] => \"alpha\"\n",
-            ".clients.hosts[1] => \"omega\"\n",
-            ".database.connection_max => 5000\n",
-            ".database.enabled => true\n",
-            ".database.ports[0] => 8000\n",
-            ".database.ports[1] => 8001\n",
-            ".database.ports[2] => 8002\n",
-            ".database.server => \"192.168.1.1\"\n",
-            ".owner.dob.\"$__toml_private_datetime\" => \"1979-05-27T07:32:00-08:00\"\n",
-            ".owner.name => \"Tom Preston-Werner\"\n",
-            ".servers.alpha.dc => \"eqdc10\"\n",
-            ".servers.alpha.ip => \"10.0.0.1\"\n",
-            ".servers.beta.dc => \"eqdc10\"\n",
-            ".servers.beta.ip => \"10.0.0.2\"\n",
-            ".title => \"TOML Example\"\n",
+            "[0].apiVersion => \"v1\"\n",
+            "[0].kind => \"Namespace\"\n",
+            "[0].metadata.name => \"demo\"\n",
+            "[1].apiVersion => \"apps/v1\"\n",
+            "[1].kind => \"Deployment\"\n",
+            "[1].metadata.name => \"web\"\n",
+            "[1].metadata.namespace => \"demo\"\n",
+            "[1].spec.replicas => 2\n",
         );
 
         assert_eq!(stdout, expected_output);
     }
-}
-
-mod test_color {
-    use assert_cmd::Command;
 
     #[test]
-    fn test_with_color() {
+    fn test_multi_document_yaml_with_document_id() {
         let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
             .unwrap()
-            .args(["-c", "always"])
-            .write_stdin("{\"foo\": \"bar\"}")
+            .args(["--document-id", "kind/metadata.name"])
+            .arg("./tests/example-multi.yaml")
             .assert();
 
         let output = assert.get_output().stdout.clone();
@@ -225,13 +586,365 @@ mod test_color {
 
         let stdout = String::from_utf8(output).unwrap();
         let expected_output = concat!(
-            "\u{1b}[0m\u{1b}[34m.foo\u{1b}[0m => \u{1b}[0m\u{1b}[32m\"bar\"\n",
-            "\u{1b}[0m",
+            "[\"Namespace/demo\"].apiVersion => \"v1\"\n",
+            "[\"Namespace/demo\"].kind => \"Namespace\"\n",
+            "[\"Namespace/demo\"].metadata.name => \"demo\"\n",
+            "[\"Deployment/web\"].apiVersion => \"apps/v1\"\n",
+            "[\"Deployment/web\"].kind => \"Deployment\"\n",
+            "[\"Deployment/web\"].metadata.name => \"web\"\n",
+            "[\"Deployment/web\"].metadata.namespace => \"demo\"\n",
+            "[\"Deployment/web\"].spec.replicas => 2\n",
         );
 
         assert_eq!(stdout, expected_output);
     }
-}
+
+    #[test]
+    fn test_toml() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .arg("./tests/example.toml")
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        let expected_output = concat!(
+            ".title => \"TOML Example\"\n",
+            ".owner.name => \"Tom Preston-Werner\"\n",
+            ".owner.dob => 1979-05-27T07:32:00-08:00\n",
+            ".database.server => \"192.168.1.1\"\n",
+            ".database.ports[0] => 8000\n",
+            ".database.ports[1] => 8001\n",
+            ".database.ports[2] => 8002\n",
+            ".database.connection_max => 5000\n",
+            ".database.enabled => true\n",
+            ".servers.alpha.ip => \"10.0.0.1\"\n",
+            ".servers.alpha.dc => \"eqdc10\"\n",
+            ".servers.beta.ip => \"10.0.0.2\"\n",
+            ".servers.beta.dc => \"eqdc10\"\n",
+            ".clients.data[0][0] => \"gamma\"\n",
+            ".clients.data[0][1] => \"delta\"\n",
+            ".clients.data[1][0] => 1\n",
+            ".clients.data[1][1] => 2\n",
+            ".clients.hosts[0] => \"alpha\"\n",
+            ".clients.hosts[1] => \"omega\"\n",
+        );
+
+        assert_eq!(stdout, expected_output);
+    }
+}
+
+#[cfg(test)]
+mod test_detect_from_content {
+    use assert_cmd::Command;
+
+    #[test]
+    fn test_yaml_from_stdin() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .arg("--verbose")
+            .write_stdin("foo: bar\n")
+            .assert();
+
+        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+        let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+        assert.success();
+
+        assert_eq!(stdout, ".foo => \"bar\"\n");
+        assert_eq!(stderr, "Detected Yaml input from content\n");
+    }
+
+    #[test]
+    fn test_toml_from_stdin() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .write_stdin("[foo]\nbar = 1\n")
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        assert_eq!(stdout, ".foo.bar => 1\n");
+    }
+
+    #[test]
+    fn test_jsonl_from_stdin() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .write_stdin("{\"foo\": 1}\n{\"foo\": 2}\n")
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        assert_eq!(stdout, "[0].foo => 1\n[1].foo => 2\n");
+    }
+
+    #[test]
+    fn test_invalid_json_from_stdin() {
+        for (input, error) in [
+            (r#"{"a": 1,}"#, "expected a key at line 1 column 9"),
+            (r#"{"a": 01}"#, "invalid number at line 1 column 8"),
+        ] {
+            let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .write_stdin(input)
+                .assert();
+
+            let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+            let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+            assert.code(2);
+
+            assert_eq!(stdout, "");
+            assert!(stderr.contains(error), "{}", stderr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_sort_keys {
+    use assert_cmd::Command;
+
+    fn run(args: &[&str]) -> String {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(args)
+            .write_stdin(r#"{"item10": 1, "item2": 2, "b": {"z": 3, "a": 4}}"#)
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_input_order_by_default() {
+        let expected_output = concat!(
+            ".item10 => 1\n",
+            ".item2 => 2\n",
+            ".b.z => 3\n",
+            ".b.a => 4\n"
+        );
+
+        assert_eq!(run(&[]), expected_output);
+    }
+
+    #[test]
+    fn test_lexicographic() {
+        let expected_output = concat!(
+            ".b.a => 4\n",
+            ".b.z => 3\n",
+            ".item10 => 1\n",
+            ".item2 => 2\n"
+        );
+
+        assert_eq!(run(&["--sort-keys"]), expected_output);
+        assert_eq!(run(&["--sort-keys=lexicographic"]), expected_output);
+    }
+
+    #[test]
+    fn test_natural() {
+        let expected_output = concat!(
+            ".b.a => 4\n",
+            ".b.z => 3\n",
+            ".item2 => 2\n",
+            ".item10 => 1\n"
+        );
+
+        assert_eq!(run(&["--sort-keys=natural"]), expected_output);
+    }
+
+    #[test]
+    fn test_sort_keys_before_file() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["--sort-keys", "./tests/example.toml"])
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        assert!(stdout.starts_with(".clients.data[0][0] => \"gamma\"\n"));
+        assert!(stdout.ends_with(".title => \"TOML Example\"\n"));
+    }
+}
+
+mod test_color {
+    use assert_cmd::Command;
+
+    #[test]
+    fn test_with_color() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["-c", "always"])
+            .write_stdin("{\"foo\": \"bar\"}")
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        let expected_output = concat!(
+            "\u{1b}[0m\u{1b}[34m.foo\u{1b}[0m => \u{1b}[0m\u{1b}[32m\"bar\"\n",
+            "\u{1b}[0m",
+        );
+
+        assert_eq!(stdout, expected_output);
+    }
+}
+
+#[cfg(test)]
+mod test_path_style {
+    use assert_cmd::Command;
+
+    fn run(path_style: &str) -> String {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["--path-style", path_style])
+            .write_stdin(r#"{"foo": {"first name": ["bar"], "a/b~c": 1}}"#)
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_jq() {
+        let expected_output = concat!(
+            ".foo.\"first name\"[0] => \"bar\"\n",
+            ".foo.\"a/b~c\" => 1\n",
+        );
+
+        assert_eq!(run("jq"), expected_output);
+    }
+
+    #[test]
+    fn test_jsonpath() {
+        let expected_output = concat!(
+            "$.foo['first name'][0] => \"bar\"\n",
+            "$.foo['a/b~c'] => 1\n",
+        );
+
+        assert_eq!(run("jsonpath"), expected_output);
+    }
+
+    #[test]
+    fn test_pointer() {
+        let expected_output = concat!("/foo/first name/0 => \"bar\"\n", "/foo/a~1b~0c => 1\n",);
+
+        assert_eq!(run("pointer"), expected_output);
+    }
+
+    #[test]
+    fn test_bracket() {
+        let expected_output = concat!(
+            "[\"foo\"][\"first name\"][0] => \"bar\"\n",
+            "[\"foo\"][\"a/b~c\"] => 1\n",
+        );
+
+        assert_eq!(run("bracket"), expected_output);
+    }
+}
+
+#[cfg(test)]
+mod test_gron {
+    use assert_cmd::Command;
+
+    #[test]
+    fn test_gron_output() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .arg("--gron")
+            .write_stdin(r#"{"foo": {"first name": ["bar"], "empty": {}}, "n": null}"#)
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        let expected_output = concat!(
+            "json = {};\n",
+            "json.foo = {};\n",
+            "json.foo[\"first name\"] = [];\n",
+            "json.foo[\"first name\"][0] = \"bar\";\n",
+            "json.foo.empty = {};\n",
+            "json.n = null;\n",
+        );
+
+        assert_eq!(stdout, expected_output);
+    }
+}
+
+#[cfg(test)]
+mod test_stream {
+    use assert_cmd::Command;
+
+    #[test]
+    fn test_jsonl() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["--stream", "-p", "user", "./tests/example.jsonl"])
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        let expected_output = concat!(
+            "[0].user.id => 42\n",
+            "[0].user.name => \"John Doe\"\n",
+            "[1].user.id => 43\n",
+            "[1].user.name => \"Jane Doe\"\n",
+        );
+
+        assert_eq!(stdout, expected_output);
+    }
+
+    #[test]
+    fn test_yaml_is_loaded() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["--stream", "-p", "alias", "./tests/example.yaml"])
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        assert!(stdout.ends_with(".alias.bar => \"baz\"\n.alias_reuse.bar => \"baz\"\n"));
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["--stream", "-t", "json"])
+            .write_stdin("{\"foo\": [1, 2}")
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+        assert.failure();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            ".foo[0] => 1\n.foo[1] => 2\n"
+        );
+        assert!(
+            stderr.contains("expected ',' or ']' at line 1 column 14"),
+            "{}",
+            stderr
+        );
+    }
+}
 
 #[cfg(test)]
 mod test_regex {
@@ -251,12 +964,666 @@ mod test_regex {
 
         let stdout = String::from_utf8(output).unwrap();
         let expected_output = concat!(
+            ".object.array[3].alias => \"aliases are like variables\"\n",
+            ".object.array[4].alias => \"aliases are like variables\"\n",
+            ".alias.bar => \"baz\"\n",
+            ".alias_reuse.bar => \"baz\"\n",
+        );
+
+        assert_eq!(stdout, expected_output);
+    }
+
+    #[test]
+    fn test_path_and_value_patterns() {
+        let input = r#"{"spec": {"name": "web", "image": "Nginx"}, "name": "nginx"}"#;
+
+        for (args, expected_output) in [
+            (
+                vec!["--path-pattern", r"^\.spec", "--color", "always"],
+                "\u{1b}[0m\u{1b}[1m\u{1b}[31m.spec\u{1b}[0m\u{1b}[0m\u{1b}[34m.name\u{1b}[0m => \u{1b}[0m\u{1b}[32m\"web\"\n\u{1b}[0m\
+                 \u{1b}[0m\u{1b}[1m\u{1b}[31m.spec\u{1b}[0m\u{1b}[0m\u{1b}[34m.image\u{1b}[0m => \u{1b}[0m\u{1b}[32m\"Nginx\"\n\u{1b}[0m",
+            ),
+            (
+                vec!["--value-pattern", "nginx"],
+                ".name => \"nginx\"\n",
+            ),
+            (
+                vec!["--value-pattern", "nginx", "-i"],
+                ".spec.image => \"Nginx\"\n.name => \"nginx\"\n",
+            ),
+            (
+                vec!["--path-pattern", "name", "--value-pattern", "web"],
+                ".spec.name => \"web\"\n",
+            ),
+            (
+                vec!["-p", "web", "-p", "image"],
+                ".spec.name => \"web\"\n.spec.image => \"Nginx\"\n",
+            ),
+            (
+                vec!["-v", "--path-pattern", "spec"],
+                ".name => \"nginx\"\n",
+            ),
+        ] {
+            let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .args(["-t", "json"])
+                .args(args)
+                .write_stdin(input)
+                .assert();
+
+            let output = assert.get_output().stdout.clone();
+            assert.success();
+
+            let stdout = String::from_utf8(output).unwrap();
+            assert_eq!(stdout, expected_output);
+        }
+    }
+
+    #[test]
+    fn test_exit_status() {
+        for (args, code, prints) in [
+            (vec!["-p", "alias"], 0, true),
+            (vec!["-p", "no such value"], 1, false),
+            (vec!["-q", "-p", "alias"], 0, false),
+            (vec!["-q", "-p", "no such value"], 1, false),
+        ] {
+            let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .arg("./tests/example.json")
+                .args(args)
+                .assert();
+
+            let output = assert.get_output().stdout.clone();
+            assert.code(code);
+
+            assert_eq!(!output.is_empty(), prints);
+        }
+    }
+
+    #[test]
+    fn test_errors_exit_with_status_2() {
+        Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .arg("./tests/no-such-file.json")
+            .assert()
+            .code(2);
+    }
+
+    #[test]
+    fn test_count() {
+        for (args, expected_output) in [
+            (vec!["--count", "-p", "alias"], "4\n"),
+            (vec!["--count", "-p", "no such value"], "0\n"),
+            (vec!["--count", "-m", "3", "-p", "alias"], "3\n"),
+            (
+                vec!["--count", "-p", "alias", "./tests/example.yaml"],
+                "./tests/example.json:4\n./tests/example.yaml:4\n",
+            ),
+            (
+                vec![
+                    "--count",
+                    "--no-filename",
+                    "-p",
+                    "alias",
+                    "./tests/example.yaml",
+                ],
+                "4\n4\n",
+            ),
+        ] {
+            let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .arg("./tests/example.json")
+                .args(args)
+                .assert();
+
+            let output = assert.get_output().stdout.clone();
+
+            let stdout = String::from_utf8(output).unwrap();
+            assert_eq!(stdout, expected_output);
+        }
+    }
+
+    #[test]
+    fn test_max_count_across_files() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["--no-filename", "-m", "5", "-p", "alias"])
+            .args(["./tests/example.json", "./tests/example.yaml"])
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        let expected_output = concat!(
+            ".object.array[3].alias => \"aliases are like variables\"\n",
+            ".object.array[4].alias => \"aliases are like variables\"\n",
             ".alias.bar => \"baz\"\n",
             ".alias_reuse.bar => \"baz\"\n",
             ".object.array[3].alias => \"aliases are like variables\"\n",
+        );
+
+        assert_eq!(stdout, expected_output);
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["-t", "json", "--value-pattern", "("])
+            .write_stdin("{}")
+            .assert()
+            .failure();
+    }
+
+    #[test]
+    fn test_invert_match_requires_a_pattern() {
+        Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["-t", "json", "-v"])
+            .write_stdin("{\"a\": 1}")
+            .assert()
+            .code(2);
+    }
+}
+
+#[cfg(test)]
+mod test_select {
+    use assert_cmd::Command;
+
+    #[test]
+    fn test_select() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["--select", ".object.array[*].alias", "--select", "..bar"])
+            .pipe_stdin("./tests/example.json")
+            .unwrap()
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        let expected_output = concat!(
+            ".object.array[3].alias => \"aliases are like variables\"\n",
             ".object.array[4].alias => \"aliases are like variables\"\n",
+            ".alias.bar => \"baz\"\n",
+            ".alias_reuse.bar => \"baz\"\n",
         );
 
         assert_eq!(stdout, expected_output);
     }
+
+    #[test]
+    fn test_invalid_select() {
+        Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["--select", "object"])
+            .pipe_stdin("./tests/example.json")
+            .unwrap()
+            .assert()
+            .code(2);
+    }
+}
+
+#[cfg(test)]
+mod test_exclude {
+    use assert_cmd::Command;
+
+    #[test]
+    fn test_exclude() {
+        for (args, expected_output) in [
+            (
+                vec![
+                    "--exclude",
+                    ".object.array",
+                    "--exclude",
+                    "..bar",
+                    "-p",
+                    "object|alias",
+                ],
+                ".object.key => \"value\"\n",
+            ),
+            (
+                vec![
+                    "--exclude-from",
+                    "./tests/example.exclude",
+                    "--mark-excluded",
+                    "-p",
+                    "object|alias",
+                ],
+                concat!(
+                    ".object.key => \"value\"\n",
+                    ".object.array => <excluded, 5 leaves>\n",
+                    ".alias.bar => <excluded, 1 leaf>\n",
+                    ".alias_reuse.bar => <excluded, 1 leaf>\n",
+                ),
+            ),
+        ] {
+            let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .args(args)
+                .pipe_stdin("./tests/example.json")
+                .unwrap()
+                .assert();
+
+            let output = assert.get_output().stdout.clone();
+            assert.success();
+
+            let stdout = String::from_utf8(output).unwrap();
+            assert_eq!(stdout, expected_output);
+        }
+    }
+
+    #[test]
+    fn test_exclude_all_values_of_a_tagged_container() {
+        // The tag is printed whether or not other values follow it in the document.
+        for (input, expected_output) in [
+            ("a: !Foo {b: 1}\n", ".a => !Foo\n"),
+            ("a: !Foo {b: 1}\nc: 2\n", ".a => !Foo\n.c => 2\n"),
+            ("a: !Foo {b: 1}\n---\nc: 2\n", "[0].a => !Foo\n[1].c => 2\n"),
+        ] {
+            let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .args(["-t", "yaml", "--exclude", ".a.b"])
+                .write_stdin(input)
+                .assert();
+
+            let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+            assert_eq!(stdout, expected_output, "{}", input);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_max_depth {
+    use assert_cmd::Command;
+    use serde_json::Value;
+
+    #[test]
+    fn test_max_depth() {
+        for (args, expected_output) in [
+            (
+                vec!["--max-depth", "1", "-p", "^\\.(object|alias) "],
+                concat!(
+                    ".object => {\"key\":\"value\",\"array\":[{\"null_value\":null},{\"boolean\":true},{\"integer\":1},{\"alias\":\"aliases are like variables\"},{\"alias\":\"aliases are like variables\"}]}\n",
+                    ".alias => {\"bar\":\"baz\"}\n",
+                ),
+            ),
+            (
+                vec!["--max-depth", "1", "--summarize", "-p", "^\\.(object|alias) "],
+                ".object => {2 keys}\n.alias => {1 key}\n",
+            ),
+            (
+                vec!["--max-depth", "2", "--summarize", "-p", "^\\.object"],
+                ".object.key => \"value\"\n.object.array => [5 items]\n",
+            ),
+        ] {
+            let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .args(args)
+                .pipe_stdin("./tests/example.json")
+                .unwrap()
+                .assert();
+
+            let output = assert.get_output().stdout.clone();
+            assert.success();
+
+            let stdout = String::from_utf8(output).unwrap();
+            assert_eq!(stdout, expected_output);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_max_depth() {
+        let original: Value =
+            serde_json::from_str(&std::fs::read_to_string("./tests/example.json").unwrap())
+                .unwrap();
+
+        for args in [vec!["--max-depth", "1"], vec!["--max-depth", "1", "--gron"]] {
+            let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .args(&args)
+                .arg("./tests/example.json")
+                .assert();
+            let lines = assert.get_output().stdout.clone();
+            assert.success();
+
+            let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .arg("--unflatten")
+                .args(args.iter().filter(|a| **a == "--gron"))
+                .write_stdin(lines)
+                .assert();
+            let output = assert.get_output().stdout.clone();
+            assert.success();
+
+            let rebuilt: Value = serde_json::from_slice(&output).unwrap();
+            assert_eq!(rebuilt, original, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_summaries_are_not_read_back() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .arg("--unflatten")
+            .write_stdin(".object => {2 keys}\n")
+            .assert();
+
+        let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+        assert.code(2);
+        assert!(stderr.contains("is a summary"), "{}", stderr);
+    }
+}
+
+#[cfg(test)]
+mod test_unflatten {
+    use assert_cmd::Command;
+    use serde_json::Value;
+
+    fn flatten(file: &str) -> Vec<u8> {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .arg(file)
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        output
+    }
+
+    fn unflatten(lines: Vec<u8>, output_type: &str) -> String {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["--unflatten", "-o", output_type])
+            .write_stdin(lines)
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_json() {
+        let original: Value =
+            serde_json::from_str(&std::fs::read_to_string("./tests/example.json").unwrap())
+                .unwrap();
+
+        let output = unflatten(flatten("./tests/example.json"), "json");
+        let rebuilt: Value = serde_json::from_str(&output).unwrap();
+
+        assert_eq!(rebuilt, original);
+    }
+
+    #[test]
+    fn test_round_trip_yaml() {
+        let original: Value =
+            serde_yaml::from_str(&std::fs::read_to_string("./tests/example.yaml").unwrap())
+                .unwrap();
+
+        let output = unflatten(flatten("./tests/example.yaml"), "yaml");
+        let rebuilt: Value = serde_yaml::from_str(&output).unwrap();
+
+        assert_eq!(rebuilt, original);
+    }
+
+    #[test]
+    fn test_round_trip_toml() {
+        let original: Value =
+            toml::from_str(&std::fs::read_to_string("./tests/example.toml").unwrap()).unwrap();
+
+        let output = unflatten(flatten("./tests/example.toml"), "toml");
+        let rebuilt: Value = toml::from_str(&output).unwrap();
+
+        assert_eq!(rebuilt, original);
+    }
+
+    #[test]
+    fn test_round_trip_gron() {
+        let original: Value =
+            serde_json::from_str(&std::fs::read_to_string("./tests/example.json").unwrap())
+                .unwrap();
+
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["--gron", "./tests/example.json"])
+            .assert();
+        let lines = assert.get_output().stdout.clone();
+        assert.success();
+
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["--unflatten", "--gron"])
+            .write_stdin(lines)
+            .assert();
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let rebuilt: Value = serde_json::from_slice(&output).unwrap();
+
+        assert_eq!(rebuilt, original);
+    }
+
+    #[test]
+    fn test_round_trip_path_styles() {
+        let original: Value =
+            serde_json::from_str(&std::fs::read_to_string("./tests/example.json").unwrap())
+                .unwrap();
+
+        for style in ["jq", "jsonpath", "pointer", "bracket"] {
+            let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .args(["--path-style", style, "./tests/example.json"])
+                .assert();
+            let lines = assert.get_output().stdout.clone();
+            assert.success();
+
+            let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .args(["--unflatten", "--path-style", style])
+                .write_stdin(lines)
+                .assert();
+            let output = assert.get_output().stdout.clone();
+            assert.success();
+
+            let rebuilt: Value = serde_json::from_slice(&output).unwrap();
+
+            assert_eq!(rebuilt, original, "path style {}", style);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_yaml_aliases() {
+        let rebuild = |args: &[&str]| {
+            let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .args(args)
+                .arg("./tests/example.yaml")
+                .assert();
+            let lines = assert.get_output().stdout.clone();
+            assert.success();
+
+            let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .args(["--unflatten"])
+                .args(args.iter().filter(|a| **a == "--gron"))
+                .write_stdin(lines)
+                .assert();
+            let output = assert.get_output().stdout.clone();
+            assert.success();
+
+            serde_json::from_slice::<Value>(&output).unwrap()
+        };
+
+        let expected = rebuild(&[]);
+        assert_eq!(rebuild(&["--yaml-aliases", "reference"]), expected);
+        assert_eq!(
+            rebuild(&["--yaml-aliases", "reference", "--gron"]),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_round_trip_keys_with_escapes() {
+        let input = r#"{"a => b": {"\u001b[31m\n": 1}, "c\\d/~": [2], "e =>": 3}"#;
+        let original: Value = serde_json::from_str(input).unwrap();
+
+        for style in ["jq", "jsonpath", "pointer", "bracket"] {
+            let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .args(["-t", "json", "--path-style", style])
+                .write_stdin(input)
+                .assert();
+            let lines = assert.get_output().stdout.clone();
+            assert.success();
+            assert!(!lines.contains(&0x1b), "path style {}", style);
+
+            let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .args(["--unflatten", "--path-style", style])
+                .write_stdin(lines)
+                .assert();
+            let output = assert.get_output().stdout.clone();
+            assert.success();
+
+            let rebuilt: Value = serde_json::from_slice(&output).unwrap();
+
+            assert_eq!(rebuilt, original, "path style {}", style);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_yaml_keys_that_are_not_strings() {
+        let input = "200: OK\n'200': string\n1.5: [a]\n? [a, {b: c}]\n: complex\n";
+        let original: serde_yaml::Value = serde_yaml::from_str(input).unwrap();
+
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["-t", "yaml"])
+            .write_stdin(input)
+            .assert();
+        let lines = assert.get_output().stdout.clone();
+        assert.success();
+
+        let output = unflatten(lines, "yaml");
+        let rebuilt: serde_yaml::Value = serde_yaml::from_str(&output).unwrap();
+
+        assert_eq!(rebuilt, original);
+    }
+
+    #[test]
+    fn test_round_trip_yaml_tags() {
+        let input = "name: !Sub ${Env}-bucket\narn: !GetAtt [Bucket, Arn]\nif: !If {a: !Ref b}\n";
+        let original: serde_yaml::Value = serde_yaml::from_str(input).unwrap();
+
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["-t", "yaml"])
+            .write_stdin(input)
+            .assert();
+        let lines = assert.get_output().stdout.clone();
+        assert.success();
+
+        let output = unflatten(lines, "yaml");
+        let rebuilt: serde_yaml::Value = serde_yaml::from_str(&output).unwrap();
+
+        assert_eq!(rebuilt, original);
+    }
+
+    #[test]
+    fn test_invalid_line() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .arg("--unflatten")
+            .write_stdin(".foo => 1\n.bar = 2\n")
+            .assert();
+
+        let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+        assert.failure();
+
+        assert!(stderr.contains("line 2"));
+    }
+}
+
+mod test_files {
+    use assert_cmd::Command;
+
+    #[test]
+    fn test_file_names_prefix_lines_of_several_files() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args([
+                "--path-pattern",
+                "^\\.alias\\.bar$",
+                "./tests/example.yaml",
+                "-",
+            ])
+            .write_stdin("{\"alias\": {\"bar\": 1}}")
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        let expected_output = concat!(
+            "./tests/example.yaml:.alias.bar => \"baz\"\n",
+            "(standard input):.alias.bar => 1\n",
+        );
+
+        assert_eq!(stdout, expected_output);
+    }
+
+    #[test]
+    fn test_gron_declares_containers_of_each_file() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args(["--gron", "--select", ".object.array[0]"])
+            .args(["./tests/example.json", "-"])
+            .write_stdin("{\"object\": {\"array\": [1]}}")
+            .assert();
+
+        let output = assert.get_output().stdout.clone();
+        assert.success();
+
+        let stdout = String::from_utf8(output).unwrap();
+        let expected_output = concat!(
+            "./tests/example.json:json = {};\n",
+            "./tests/example.json:json.object = {};\n",
+            "./tests/example.json:json.object.array = [];\n",
+            "./tests/example.json:json.object.array[0] = {};\n",
+            "./tests/example.json:json.object.array[0].null_value = null;\n",
+            "(standard input):json = {};\n",
+            "(standard input):json.object = {};\n",
+            "(standard input):json.object.array = [];\n",
+            "(standard input):json.object.array[0] = 1;\n",
+        );
+
+        assert_eq!(stdout, expected_output);
+    }
+
+    #[test]
+    fn test_keep_going_after_file_error() {
+        let assert = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .args([
+                "--path-pattern",
+                "^\\.alias\\.bar$",
+                "./tests/no_such_file.json",
+            ])
+            .arg("./tests/example.yaml")
+            .assert();
+
+        let output = assert.get_output().clone();
+        assert.code(2);
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(stdout, "./tests/example.yaml:.alias.bar => \"baz\"\n");
+
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.starts_with("Error: ./tests/no_such_file.json: "));
+    }
 }